@@ -1,6 +1,7 @@
 #![deny(warnings)]
 
 use intcode_computer::*;
+use rayon::prelude::*;
 use std::str::FromStr;
 
 /// Once you have a working computer, the first step is to restore the gravity assist program (your
@@ -16,23 +17,28 @@ fn compute_from_inputs(mut computer: Computer, noun: isize, verb: isize) -> Resu
     Ok(computer.data[0])
 }
 
+/// Find the noun and verb which make the program output `expected`.
+/// The search space is explored in parallel. Since several pairs may match, we reduce to the
+/// lexicographically smallest one so that the answer doesn't depend on thread scheduling.
+fn find_inputs(computer: &Computer, expected: isize) -> Option<(isize, isize)> {
+    (0..99)
+        .into_par_iter()
+        .flat_map(|noun| (0..99).into_par_iter().map(move |verb| (noun, verb)))
+        .filter(|(noun, verb)| compute_from_inputs(computer.clone(), *noun, *verb) == Ok(expected))
+        .min()
+}
+
 /// What value is left at position 0 after the program halts?
 fn main() {
     let computer = Computer::from_str(include_str!("input.txt")).unwrap();
     let part_1 = compute_from_inputs(computer.clone(), 12, 2).unwrap();
     assert_eq!(4090701, part_1);
     println!("part 1: {}", part_1);
-    for noun in 0..99 {
-        for verb in 0..99 {
-            if compute_from_inputs(computer.clone(), noun, verb) == Ok(19690720) {
-                let part_2 = 100 * noun + verb;
-                assert_eq!(6421, part_2);
-                println!("part 2: {}", part_2);
-                return;
-            }
-        }
-    }
-    panic!("Error: we didn't find a solution for part 2");
+    let (noun, verb) =
+        find_inputs(&computer, 19690720).expect("Error: we didn't find a solution for part 2");
+    let part_2 = 100 * noun + verb;
+    assert_eq!(6421, part_2);
+    println!("part 2: {}", part_2);
 }
 
 mod tests {
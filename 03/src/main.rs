@@ -1,9 +1,11 @@
 #![deny(warnings)]
 
 use direction::CardinalDirection;
+use map_display::parse_direction;
+use std::collections::HashMap;
 use std::str::FromStr;
 
-#[derive(Clone, Copy, Default, Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Default, Debug, Eq, PartialEq, Hash)]
 struct Point {
     x: i32,
     y: i32,
@@ -13,25 +15,6 @@ impl Point {
     fn manhattan_distance_to_origin(self) -> i32 {
         self.x.abs() + self.y.abs()
     }
-    /// Some if the point is on the wire
-    fn wire_distance_to_origin(self, wire: &Wire) -> Option<i32> {
-        let mut distance = 0;
-        let mut last_point = Point::origin();
-        for segment in wire.segments.iter() {
-            for point in segment.all_points_from(last_point) {
-                if point == self {
-                    return Some(distance);
-                }
-                distance += 1;
-            }
-            if last_point == segment.start {
-                last_point = segment.end();
-            } else {
-                last_point = segment.start;
-            }
-        }
-        None
-    }
     fn origin() -> Self {
         Point::default()
     }
@@ -247,6 +230,66 @@ struct Wire {
 }
 
 impl Wire {
+    fn length(&self) -> i32 {
+        self.segments.iter().map(|segment| segment.length).sum()
+    }
+    /// Where we are after tracing the wire for that distance from the origin. None past its end
+    fn point_at_distance(&self, distance: i32) -> Option<Point> {
+        if distance < 0 {
+            return None;
+        }
+        let mut remaining = distance;
+        let mut last_point = Point::origin();
+        for segment in self.segments.iter() {
+            if remaining < segment.length {
+                return Some(segment.all_points_from(last_point)[remaining as usize]);
+            }
+            remaining -= segment.length;
+            if last_point == segment.start {
+                last_point = segment.end();
+            } else {
+                last_point = segment.start;
+            }
+        }
+        if remaining == 0 {
+            Some(last_point)
+        } else {
+            None
+        }
+    }
+    /// How far along the wire each point is, the first time the wire goes through it
+    fn first_visit_distances(&self) -> HashMap<Point, i32> {
+        let mut distances = HashMap::new();
+        let mut distance = 0;
+        let mut last_point = Point::origin();
+        for segment in self.segments.iter() {
+            for point in segment.all_points_from(last_point) {
+                distances.entry(point).or_insert(distance);
+                distance += 1;
+            }
+            if last_point == segment.start {
+                last_point = segment.end();
+            } else {
+                last_point = segment.start;
+            }
+        }
+        distances
+    }
+    /// Where the wire crosses itself. Consecutive segments always share an end, so they are left
+    /// out
+    fn self_intersections(&self) -> Vec<Point> {
+        let mut crossings = Vec::new();
+        for (index, segment) in self.segments.iter().enumerate() {
+            for other_segment in self.segments.iter().skip(index + 2) {
+                if let Some(point) = segment.closest_intersection_to_origin(*other_segment) {
+                    if !crossings.contains(&point) {
+                        crossings.push(point);
+                    }
+                }
+            }
+        }
+        crossings
+    }
     fn intersections(&self, other: &Self) -> Vec<Point> {
         self.segments
             .iter()
@@ -273,32 +316,13 @@ impl Wire {
             .map(Point::manhattan_distance_to_origin)
     }
     fn wire_distance_from_closest_intersection_to_origin(&self, other: &Self) -> Option<i32> {
-        let intersections = self.intersections(other);
-        intersections
+        let (distances, other_distances) =
+            (self.first_visit_distances(), other.first_visit_distances());
+        self.intersections(other)
             .into_iter()
             .filter(|point| *point != Point::origin())
-            .min_by(|a, b| {
-                (a.wire_distance_to_origin(self).unwrap()
-                    + a.wire_distance_to_origin(other).unwrap())
-                .cmp(
-                    &(b.wire_distance_to_origin(self).unwrap()
-                        + b.wire_distance_to_origin(other).unwrap()),
-                )
-            })
-            .map(|point| {
-                point.wire_distance_to_origin(self).unwrap()
-                    + point.wire_distance_to_origin(other).unwrap()
-            })
-    }
-}
-
-fn parse_direction(c: char) -> CardinalDirection {
-    match c {
-        'U' => CardinalDirection::North,
-        'D' => CardinalDirection::South,
-        'R' => CardinalDirection::East,
-        'L' => CardinalDirection::West,
-        _ => panic!(format!("Can't parse {} as a direction!", c)),
+            .map(|point| distances[&point] + other_distances[&point])
+            .min()
     }
 }
 
@@ -309,8 +333,12 @@ impl FromStr for Wire {
         let mut start = Point::origin();
         let mut segments = Vec::new();
         for word in s.split(',') {
-            let direction = parse_direction(word.chars().next().unwrap());
-            let distance: i32 = word[1..].parse().map_err(|e| format!("{}", e))?;
+            let mut chars = word.chars();
+            let letter = chars
+                .next()
+                .ok_or_else(|| format!("Empty step in wire: '{}'", s))?;
+            let direction = parse_direction(letter)?;
+            let distance: i32 = chars.as_str().parse().map_err(|e| format!("{}", e))?;
             let end = start.travel(direction, distance);
             segments.push(Segment::from_points(start, end)?);
             start = end;
@@ -329,6 +357,16 @@ fn parse_input() -> Vec<Wire> {
 
 fn main() {
     let wires = parse_input();
+    for (index, wire) in wires.iter().enumerate() {
+        let length = wire.length();
+        println!(
+            "wire {}: {} long, ending at {:?}, crossing itself {} times",
+            index,
+            length,
+            wire.point_at_distance(length).unwrap(),
+            wire.self_intersections().len()
+        );
+    }
     let part_1 = wires[0]
         .manhattan_distance_from_closest_intersection_to_origin(&wires[1])
         .unwrap();
@@ -396,4 +434,42 @@ mod tests {
             test.run();
         }
     }
+    #[test]
+    fn test_point_at_distance() {
+        let wire = Wire::from_str("R8,U5,L5,D3").unwrap();
+        assert_eq!(21, wire.length());
+        let point = |x, y| Point { x, y };
+        assert_eq!(Some(Point::origin()), wire.point_at_distance(0));
+        assert_eq!(Some(point(8, 0)), wire.point_at_distance(8));
+        assert_eq!(Some(point(8, 2)), wire.point_at_distance(10));
+        assert_eq!(Some(point(3, 2)), wire.point_at_distance(21));
+        assert_eq!(None, wire.point_at_distance(22));
+        assert_eq!(None, wire.point_at_distance(-1));
+        let intersection = point(3, 3);
+        let distance = wire.first_visit_distances()[&intersection];
+        assert_eq!(Some(intersection), wire.point_at_distance(distance));
+    }
+    #[test]
+    fn test_self_intersections() {
+        let square = Wire::from_str("R4,U4,L4,D4").unwrap();
+        assert_eq!(vec![Point::origin()], square.self_intersections());
+        let figure_of_eight = Wire::from_str("R4,U2,L2,D4,L2,U1").unwrap();
+        assert_eq!(
+            vec![Point { x: 2, y: 0 }],
+            figure_of_eight.self_intersections()
+        );
+        let spiral = Wire::from_str("R4,U4,L4,D2,R2").unwrap();
+        assert!(spiral.self_intersections().is_empty());
+    }
+    #[test]
+    fn test_invalid_wire() {
+        assert_eq!(
+            Some("Can't parse 'X' as a direction".to_string()),
+            Wire::from_str("R8,X5").err()
+        );
+        assert_eq!(
+            Some("Empty step in wire: 'R8,,U5'".to_string()),
+            Wire::from_str("R8,,U5").err()
+        );
+    }
 }
@@ -1,12 +1,16 @@
 #![deny(warnings)]
 
-use petgraph::{algo::bellman_ford, dot::Dot, graph::NodeIndex, Direction};
-use std::{collections::HashMap, fmt};
+use petgraph::{dot::Dot, graph::NodeIndex, Direction};
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+};
+use structopt::StructOpt;
 
 #[derive(Default)]
 struct Graph {
     nodes: HashMap<&'static str, NodeIndex>,
-    graph: petgraph::graph::DiGraph<&'static str, f32>,
+    graph: petgraph::graph::DiGraph<&'static str, ()>,
 }
 impl Graph {
     fn from_edges(edges: &[(&'static str, &'static str)]) -> Self {
@@ -28,52 +32,80 @@ impl Graph {
     fn insert_edge(&mut self, edge: &(&'static str, &'static str)) {
         let in_node = self.nodes[edge.0];
         let out_node = self.nodes[edge.1];
-        self.graph.add_edge(in_node, out_node, 1.);
+        self.graph.add_edge(in_node, out_node, ());
     }
-    fn sum_orbits(&self) -> f32 {
+    /// Breadth first walk from COM: the depth of each node and the node it orbits
+    fn depths_and_parents(&self) -> (Vec<usize>, Vec<Option<NodeIndex>>) {
         let mut sources = self.graph.externals(Direction::Incoming);
-        let source_node = sources.next().unwrap();
-        assert_eq!(Some(&"COM"), self.graph.node_weight(source_node));
+        let root_node = sources.next().unwrap();
+        assert_eq!(Some(&"COM"), self.graph.node_weight(root_node));
         assert!(sources.next().is_none());
-        let (path_weights, _node_indices) = bellman_ford(&self.graph, source_node).unwrap();
-        path_weights.iter().sum()
+        let mut depths = vec![0; self.graph.node_count()];
+        let mut parents = vec![None; self.graph.node_count()];
+        let mut queue = VecDeque::new();
+        queue.push_back(root_node);
+        while let Some(node) = queue.pop_front() {
+            for child in self.graph.neighbors(node) {
+                depths[child.index()] = depths[node.index()] + 1;
+                parents[child.index()] = Some(node);
+                queue.push_back(child);
+            }
+        }
+        (depths, parents)
+    }
+    fn sum_orbits(&self) -> usize {
+        let (depths, _parents) = self.depths_and_parents();
+        depths.iter().sum()
     }
-    fn shortest_path(
-        source_node: NodeIndex,
-        destination_node: NodeIndex,
-        bellman_ford: (Vec<f32>, Vec<Option<NodeIndex>>),
-    ) -> Vec<NodeIndex> {
-        let (_path_weights, paths) = bellman_ford;
-        let mut next = destination_node;
-        let mut path = Vec::new();
-        path.push(next);
-        while let Some(current) = paths[next.index()] {
-            path.push(current);
-            if current == source_node {
-                return path;
+    /// The deepest node which both a and b orbit, directly or indirectly (or are).
+    /// None if either node is unknown
+    fn lca(&self, a: &str, b: &str) -> Option<&str> {
+        let (depths, parents) = self.depths_and_parents();
+        let lca = Self::lca_of(&depths, &parents, *self.nodes.get(a)?, *self.nodes.get(b)?)?;
+        self.graph.node_weight(lca).cloned()
+    }
+    fn lca_of(
+        depths: &[usize],
+        parents: &[Option<NodeIndex>],
+        mut a: NodeIndex,
+        mut b: NodeIndex,
+    ) -> Option<NodeIndex> {
+        while a != b {
+            if depths[a.index()] >= depths[b.index()] {
+                a = parents[a.index()]?;
+            } else {
+                b = parents[b.index()]?;
             }
-            next = current;
         }
-        path
+        Some(a)
+    }
+    /// One line per object, indented two spaces deeper than the object it orbits. Siblings are
+    /// sorted by name
+    fn to_ascii_tree(&self) -> String {
+        let mut tree = String::new();
+        if let Some(com) = self.nodes.get("COM") {
+            self.write_subtree(*com, 0, &mut tree);
+        }
+        tree
     }
-    fn min_num_of_orbital_transfers(
-        &self,
-        start: &'static str,
-        destination: &'static str,
-    ) -> usize {
-        let root_node = self.nodes["COM"];
-        let source_node = self.nodes[start];
-        let destination_node = self.nodes[destination];
-        let bf = bellman_ford(&self.graph, root_node).unwrap();
-        let mut root_to_start = Self::shortest_path(root_node, source_node, bf.clone());
-        let mut root_to_destination = Self::shortest_path(root_node, destination_node, bf);
-        let (mut to_start, mut to_destination) = (root_to_start.pop(), root_to_destination.pop());
-        while to_start.is_some() && to_start == to_destination {
-            to_start = root_to_start.pop();
-            to_destination = root_to_destination.pop();
+    fn write_subtree(&self, node: NodeIndex, depth: usize, tree: &mut String) {
+        tree.push_str(&format!("{}{}\n", "  ".repeat(depth), self.graph[node]));
+        let mut children = self.graph.neighbors(node).collect::<Vec<_>>();
+        children.sort_by_key(|child| self.graph[*child]);
+        for child in children {
+            self.write_subtree(child, depth + 1, tree);
         }
-        // We've removed all common ancestors
-        root_to_start.len() + root_to_destination.len()
+    }
+    /// Transfers happen between the objects orbited by start and destination, so this is the
+    /// distance between those. None if either is unknown or is COM, which orbits nothing
+    fn min_num_of_orbital_transfers(&self, start: &str, destination: &str) -> Option<usize> {
+        let (depths, parents) = self.depths_and_parents();
+        let start = parents[self.nodes.get(start)?.index()]?;
+        let destination = parents[self.nodes.get(destination)?.index()]?;
+        let lca = Self::lca_of(&depths, &parents, start, destination)?;
+        let depth = |node: NodeIndex| depths[node.index()];
+        // The common ancestor is no deeper than either end
+        Some(depth(start) + depth(destination) - 2 * depth(lca))
     }
 }
 
@@ -98,15 +130,30 @@ fn parse_input(data: &'static str) -> Graph {
     Graph::from_edges(&edges)
 }
 
+#[derive(Debug, StructOpt)]
+#[structopt(name = "orbits", about = "Universal Orbit Map.")]
+struct Opt {
+    /// Print the orbit map as an indented tree rooted at COM
+    #[structopt(long)]
+    tree: bool,
+}
+
 fn main() {
     let graph = parse_input(include_str!("input.txt"));
+    if Opt::from_args().tree {
+        print!("{}", graph.to_ascii_tree());
+    }
     let part_1 = graph.sum_orbits();
-    assert_eq!(344238., part_1);
+    assert_eq!(344238, part_1);
     println!("part 1: {}", part_1);
 
-    let part_2 = graph.min_num_of_orbital_transfers("YOU", "SAN");
+    let part_2 = graph.min_num_of_orbital_transfers("YOU", "SAN").unwrap();
     assert_eq!(436, part_2);
-    println!("part 2: {}", part_2);
+    println!(
+        "part 2: {} (via {})",
+        part_2,
+        graph.lca("YOU", "SAN").unwrap()
+    );
 }
 
 #[cfg(test)]
@@ -129,6 +176,28 @@ K)YOU
 I)SAN";
 
         let graph = parse_input(input);
-        assert_eq!(4, graph.min_num_of_orbital_transfers("YOU", "SAN"));
+        assert_eq!(54, graph.sum_orbits());
+        assert_eq!(Some("D"), graph.lca("YOU", "SAN"));
+        assert_eq!(Some("K"), graph.lca("K", "YOU"));
+        assert_eq!(None, graph.lca("YOU", "nowhere"));
+        assert_eq!(Some(4), graph.min_num_of_orbital_transfers("YOU", "SAN"));
+        // From the object D orbits to the one YOU orbits: C, D, E, J, K
+        assert_eq!(Some(4), graph.min_num_of_orbital_transfers("D", "YOU"));
+        assert_eq!(Some(4), graph.min_num_of_orbital_transfers("YOU", "D"));
+        assert_eq!(Some(0), graph.min_num_of_orbital_transfers("YOU", "YOU"));
+        assert_eq!(Some(0), graph.min_num_of_orbital_transfers("C", "G"));
+        assert_eq!(None, graph.min_num_of_orbital_transfers("COM", "YOU"));
+        assert_eq!(None, graph.min_num_of_orbital_transfers("YOU", "nowhere"));
+        let tree = graph.to_ascii_tree();
+        let depth_of = |name: &str| {
+            tree.lines()
+                .find(|line| line.trim() == name)
+                .map(|line| (line.len() - line.trim_start().len()) / 2)
+        };
+        assert!(tree.starts_with("COM\n  B\n"));
+        assert_eq!(Some(0), depth_of("COM"));
+        assert_eq!(Some(7), depth_of("YOU"));
+        assert_eq!(Some(5), depth_of("SAN"));
+        assert_eq!(14, tree.lines().count());
     }
 }
@@ -9,78 +9,148 @@ struct Amplifiers {
 
 impl Amplifiers {
     fn new(computer: &Computer, phase_settings: &[isize]) -> Self {
-        let mut computers = (0..5).map(|_| computer.clone()).collect::<Vec<_>>();
-        for (index, input) in phase_settings.iter().enumerate() {
-            let computer = &mut computers[index];
-            computer.set_mock_io_input(&format!("{}\n", input));
+        let programs = phase_settings.iter().map(|_| computer.clone()).collect();
+        Self::from_programs(programs, phase_settings).unwrap()
+    }
+    /// One stage per program, each one fed its own phase setting
+    fn from_programs(
+        mut computers: Vec<Computer>,
+        phase_settings: &[isize],
+    ) -> Result<Self, String> {
+        if computers.len() != phase_settings.len() {
+            return Err(format!(
+                "Got {} programs for {} phase settings",
+                computers.len(),
+                phase_settings.len()
+            ));
+        }
+        for (computer, input) in computers.iter_mut().zip(phase_settings) {
+            computer.set_mock_io_input(&format!("{}\n", input))?;
             let status = computer.compute().unwrap();
-            assert!(status != ComputationStatus::Done);
+            assert!(!status.is_done());
         }
-        Self { computers }
+        Ok(Self { computers })
     }
     fn amplify(&mut self, input: isize) -> Result<AmplificationStatus, String> {
         let mut signal = input;
         let mut status = ComputationStatus::StarvingForMockInput;
         for computer in self.computers.iter_mut() {
-            computer.set_mock_io_input(&format!("{}", signal));
+            computer.set_mock_io_input(&format!("{}", signal))?;
             status = computer.compute()?;
-            signal = computer.get_mock_io_output()?.trim().parse().unwrap();
+            let output = computer.get_mock_io_output()?;
+            if output.trim().is_empty() && status.is_done() {
+                // This stage halted without emitting anything: there is nothing left to
+                // propagate, so the last valid signal stands
+                break;
+            }
+            signal = output
+                .trim()
+                .parse()
+                .map_err(|e| format!("Invalid signal '{}': {}", output.trim(), e))?;
         }
         Ok(AmplificationStatus { signal, status })
     }
 }
 
-#[derive(Default)]
 struct AmplificationStatus {
     signal: isize,
     status: ComputationStatus,
 }
 
+/// The highest signal run produces over every ordering of the phases, and the phases producing it
+fn best_over_permutations(
+    computer: &Computer,
+    phases: std::ops::RangeInclusive<isize>,
+    run: impl Fn(&Computer, &[isize]) -> isize,
+) -> (isize, Vec<isize>) {
+    use itertools::Itertools;
+    let num_phases = phases.clone().count();
+    phases
+        .permutations(num_phases)
+        .map(|permutation| (run(computer, &permutation), permutation))
+        .max_by_key(|(signal, _permutation)| *signal)
+        .unwrap()
+}
+
 mod amplify_once {
     use super::*;
+    #[cfg(test)]
     pub(super) fn max_thruster_signal(computer: Computer) -> isize {
-        use itertools::Itertools;
-        (0..=4)
-            .permutations(5)
-            .map(|permutation| amplify_chain(&computer, &permutation))
-            .max()
-            .unwrap()
+        best_configuration(computer).0
+    }
+    /// The highest signal which can be sent to the thrusters, and the phase settings producing it
+    pub(super) fn best_configuration(computer: Computer) -> (isize, Vec<isize>) {
+        best_over_permutations(&computer, 0..=4, amplify_chain)
     }
     fn amplify_chain(computer: &Computer, amplifier_inputs: &[isize]) -> isize {
         let mut amps = Amplifiers::new(computer, amplifier_inputs);
-        amps.amplify(0).unwrap().signal
+        let res = amps.amplify(0).unwrap();
+        // Without a feedback loop, a single pass runs every amplifier to completion
+        assert_eq!(ComputationStatus::Done, res.status);
+        res.signal
     }
 }
 
 mod feedback_loop {
     use super::*;
+    use std::iter;
+    use std::sync::mpsc::channel;
+    use std::thread;
+
+    /// Run each amplifier on its own thread, each stage feeding the next one through a channel.
+    /// The thrusters' signals are collected here and fed back to the first stage.
     pub(super) fn amplify_chain(computer: &Computer, amplifier_inputs: &[isize]) -> isize {
-        let mut amps = Amplifiers::new(computer, amplifier_inputs);
-        let mut res = AmplificationStatus::default();
-        while res.status != ComputationStatus::Done {
-            res = amps.amplify(res.signal).unwrap();
+        let (senders, receivers): (Vec<_>, Vec<_>) =
+            amplifier_inputs.iter().map(|_| channel()).unzip();
+        for (sender, phase) in senders.iter().zip(amplifier_inputs) {
+            sender.send(*phase).unwrap();
         }
-        res.signal
+        let (thrusters, results) = channel();
+        let mut senders = senders.into_iter();
+        let first_stage = senders.next().unwrap();
+        let stages = receivers
+            .into_iter()
+            .zip(senders.chain(iter::once(thrusters)))
+            .map(|(input, output)| {
+                let mut computer = computer.clone().with_io(ChannelIo { input, output });
+                thread::spawn(move || computer.compute())
+            })
+            .collect::<Vec<_>>();
+        let mut signal = 0;
+        first_stage.send(signal).unwrap();
+        // Ends once the last stage halted and hung up
+        for result in results {
+            signal = result;
+            // The first stage may have halted already, in which case nobody is listening
+            let _ = first_stage.send(signal);
+        }
+        drop(first_stage);
+        for stage in stages {
+            stage.join().unwrap().unwrap();
+        }
+        signal
     }
 
+    #[cfg(test)]
     pub(super) fn max_thruster_signal(computer: Computer) -> isize {
-        use itertools::Itertools;
-        (5..=9)
-            .permutations(5)
-            .map(|permutation| amplify_chain(&computer, &permutation))
-            .max()
-            .unwrap()
+        best_configuration(computer).0
+    }
+    /// The highest signal which can be sent to the thrusters, and the phase settings producing it
+    pub(super) fn best_configuration(computer: Computer) -> (isize, Vec<isize>) {
+        best_over_permutations(&computer, 5..=9, amplify_chain)
     }
 }
 
 fn main() {
     let computer = Computer::from_str(include_str!("input.txt")).unwrap();
-    let part_1 = amplify_once::max_thruster_signal(computer.clone());
+    let (part_1, phases) = amplify_once::best_configuration(computer.clone());
     assert_eq!(46248, part_1);
-    println!("part 1: {}", part_1);
-    let part_2 = feedback_loop::max_thruster_signal(computer.clone());
+    assert_eq!(vec![1, 0, 2, 4, 3], phases);
+    println!("part 1: {} (phases: {:?})", part_1, phases);
+    let (part_2, phases) = feedback_loop::best_configuration(computer.clone());
     assert_eq!(54163586, part_2);
-    println!("part 2: {}", part_2);
+    assert_eq!(vec![6, 8, 5, 9, 7], phases);
+    println!("part 2: {} (phases: {:?})", part_2, phases);
 }
 
 #[cfg(test)]
@@ -130,6 +200,64 @@ mod tests {
             );
         }
     }
+    #[test]
+    fn test_amplify_stage_halting_without_output() {
+        // Read the phase, then the input signal, then halt without outputting anything
+        let computer = Computer::from_data(vec![3, 5, 3, 5, 99, 0]);
+        let mut amps = Amplifiers::new(&computer, &[0, 1, 2, 3, 4]);
+        let res = amps.amplify(42).unwrap();
+        assert_eq!(42, res.signal);
+        assert_eq!(ComputationStatus::Done, res.status);
+    }
+    #[test]
+    fn test_amplify_different_programs() {
+        // Read the phase, then the input signal, and output their sum or product
+        let add = Computer::from_data(vec![3, 11, 3, 12, 1, 11, 12, 13, 4, 13, 99, 0, 0, 0]);
+        let mul = Computer::from_data(vec![3, 11, 3, 12, 2, 11, 12, 13, 4, 13, 99, 0, 0, 0]);
+        let programs = vec![add.clone(), mul, add];
+        let mut amps = Amplifiers::from_programs(programs.clone(), &[1, 10, 3]).unwrap();
+        let res = amps.amplify(2).unwrap();
+        assert_eq!((2 + 1) * 10 + 3, res.signal);
+        assert_eq!(ComputationStatus::Done, res.status);
+        assert_eq!(
+            Some("Got 3 programs for 2 phase settings".to_string()),
+            Amplifiers::from_programs(programs, &[1, 10]).err()
+        );
+    }
+    #[test]
+    fn test_best_over_permutations() {
+        let computer = Computer::from_data(vec![99]);
+        let as_number = |_: &Computer, phases: &[isize]| {
+            phases.iter().fold(0, |number, phase| number * 10 + phase)
+        };
+        assert_eq!(
+            (321, vec![3, 2, 1]),
+            best_over_permutations(&computer, 1..=3, as_number)
+        );
+        let reversed = |computer: &Computer, phases: &[isize]| -as_number(computer, phases);
+        assert_eq!(
+            (-123, vec![1, 2, 3]),
+            best_over_permutations(&computer, 1..=3, reversed)
+        );
+    }
+    #[test]
+    fn test_best_configuration() {
+        let computer = Computer::from_data(vec![
+            3, 15, 3, 16, 1002, 16, 10, 16, 1, 16, 15, 15, 4, 15, 99, 0, 0,
+        ]);
+        assert_eq!(
+            (43210, vec![4, 3, 2, 1, 0]),
+            amplify_once::best_configuration(computer)
+        );
+        let computer = Computer::from_data(vec![
+            3, 26, 1001, 26, -4, 26, 3, 27, 1002, 27, 2, 27, 1, 27, 26, 27, 4, 27, 1001, 28, -1,
+            28, 1005, 28, 6, 99, 0, 0, 5,
+        ]);
+        assert_eq!(
+            (139629729, vec![9, 8, 7, 6, 5]),
+            feedback_loop::best_configuration(computer)
+        );
+    }
     struct AmpTestCase {
         computer: Computer,
         amp: Vec<isize>,
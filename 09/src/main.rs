@@ -8,7 +8,7 @@ fn main() {
     {
         // 1: test mode
         let mut computer = computer.clone();
-        computer.set_mock_io_input("1");
+        computer.set_mock_io_input("1").unwrap();
         computer.compute().unwrap();
         let output = computer.get_mock_io_output().unwrap();
         assert_eq!("2171728567", output.trim());
@@ -17,7 +17,7 @@ fn main() {
     {
         // 2: sensor boost mode
         let mut computer = computer.clone();
-        computer.set_mock_io_input("2");
+        computer.set_mock_io_input("2").unwrap();
         computer.compute().unwrap();
         let output = computer.get_mock_io_output().unwrap();
         assert_eq!("49815", output.trim());
@@ -30,32 +30,34 @@ mod tests {
     use super::*;
     #[test]
     fn test_self_replicating_computer() {
+        let input = "109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99";
+        let computer = Computer::from_str(input).unwrap();
+        assert!(computer.clone().is_quine());
+        let output = computer.clone().run_and_collect(&[]).unwrap();
+        assert_eq!(computer.data, output);
+    }
+    #[test]
+    fn test_profile_self_replicating_computer() {
+        use intcode_computer::Operation;
         let input = "109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99".to_string();
         let mut computer = Computer::from_str(&input).unwrap();
-        computer.set_mock_io_input("");
+        computer.enable_profiling();
+        computer.set_mock_io_input("").unwrap();
         computer.compute().unwrap();
-        let output = computer
-            .get_mock_io_output()
-            .unwrap()
-            .trim()
-            .replace("\n", ",")
-            .to_string();
-        assert_eq!(input.to_string(), output);
+        let stats = computer.op_stats();
+        // One output per cell of the program
+        assert_eq!(16, stats[&Operation::Output]);
+        assert_eq!(1, stats[&Operation::End]);
+        assert_eq!(stats.values().sum::<u64>(), computer.total_instructions());
     }
     #[test]
     fn test_large_value() {
         let mut computer = Computer::from_str("1102,34915192,34915192,7,4,7,99,0").unwrap();
-        computer.set_mock_io_input("");
-        computer.compute().unwrap();
-        let output = computer.get_mock_io_output();
-        assert_eq!(Ok("1219070632396864\n".to_string()), output);
+        assert_eq!(Ok(vec![1219070632396864]), computer.run_and_collect(&[]));
     }
     #[test]
     fn test_print_middle_value() {
         let mut computer = Computer::from_str("104,1125899906842624,99").unwrap();
-        computer.set_mock_io_input("");
-        computer.compute().unwrap();
-        let output = computer.get_mock_io_output();
-        assert_eq!(Ok("1125899906842624\n".to_string()), output);
+        assert_eq!(Ok(vec![1125899906842624]), computer.run_and_collect(&[]));
     }
 }
@@ -1,11 +1,16 @@
 #![deny(warnings)]
 
+use direction::Coord;
 use fraction::{GenericFraction, Sign};
+use map_display::{bounds, MapDisplay};
 use multimap::MultiMap;
 use std::cmp::Ordering;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
 use std::fmt;
 use std::str::FromStr;
+use std::time::Instant;
+use structopt::StructOpt;
 
 type Fraction = GenericFraction<usize>;
 
@@ -22,6 +27,14 @@ impl Point {
     fn in_box(&self, n_cols: usize, n_rows: usize) -> bool {
         self.col < n_cols && self.row < n_rows
     }
+    fn manhattan_distance(self, other: Point) -> usize {
+        self.col.abs_diff(other.col) + self.row.abs_diff(other.row)
+    }
+    /// The square of the euclidean distance, which orders points the same way
+    fn distance_squared(self, other: Point) -> usize {
+        let (d_col, d_row) = (self.col.abs_diff(other.col), self.row.abs_diff(other.row));
+        d_col * d_col + d_row * d_row
+    }
 }
 
 impl Ord for Point {
@@ -58,8 +71,8 @@ impl Line {
         if x >= y {
             panic!("Expected {:?} to be < than {:?}", x, y);
         }
-        let numer = (y.row - x.row) as isize;
-        let denom = (x.col - y.col) as isize;
+        let numer = y.row as isize - x.row as isize;
+        let denom = x.col as isize - y.col as isize;
         let sign = numer.signum() * denom.signum();
         let numer = numer.abs() as usize;
         let denom = denom.abs() as usize;
@@ -76,24 +89,28 @@ impl Line {
         let slope = Self::calculate_slope(origin, other);
         Self { origin, slope }
     }
-    fn next_point(&self, last: Point) -> Point {
-        match self.slope {
-            GenericFraction::Infinity(_) => Point::new(last.col, last.row + 1),
-            GenericFraction::Rational(Sign::Minus, ratio) => {
-                Point::new(last.col + ratio.denom(), last.row + ratio.numer())
-            }
-            GenericFraction::Rational(Sign::Plus, ratio) => {
-                Point::new(last.col - ratio.denom(), last.row + ratio.numer())
-            }
+    /// None once the line leaves the range of coordinates a Point can hold
+    fn next_point(&self, last: Point) -> Option<Point> {
+        let (col, row) = match self.slope {
+            GenericFraction::Infinity(_) => (Some(last.col), last.row.checked_add(1)),
+            GenericFraction::Rational(Sign::Minus, ratio) => (
+                last.col.checked_add(*ratio.denom()),
+                last.row.checked_add(*ratio.numer()),
+            ),
+            GenericFraction::Rational(Sign::Plus, ratio) => (
+                last.col.checked_sub(*ratio.denom()),
+                last.row.checked_add(*ratio.numer()),
+            ),
             GenericFraction::NaN => panic!("Not a number"),
-        }
+        };
+        Some(Point::new(col?, row?))
     }
     fn points(&self, n_cols: usize, n_rows: usize) -> Vec<Point> {
         let mut points = vec![];
-        let mut last_point = self.origin;
-        while last_point.in_box(n_cols, n_rows) {
-            points.push(last_point);
-            last_point = self.next_point(last_point);
+        let mut last_point = Some(self.origin);
+        while let Some(point) = last_point.filter(|point| point.in_box(n_cols, n_rows)) {
+            points.push(point);
+            last_point = self.next_point(point);
         }
         points
     }
@@ -119,6 +136,23 @@ impl Line {
     }
 }
 
+/// How to find out which asteroids can be seen from one another
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum LineOfSight {
+    /// Build every line going through at least two asteroids
+    AllLines,
+    /// Count the distinct directions towards the other asteroids
+    ReducedDirections,
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 enum GridSection {
     UpperHalf,
@@ -137,47 +171,70 @@ impl FromStr for AsteroidMap {
     type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let lines = s.split('\n');
-        let n_rows = lines.clone().count();
-        let mut n_cols = 0;
-        let positions = lines
-            .enumerate()
-            .flat_map(|(row, line)| {
-                if row == 0 {
-                    n_cols = line.len();
-                } else {
-                    if n_cols != line.len() {
-                        return vec![Err(format!(
-                            "Inconsistent row lengths: row 0 has {} cols while row {} has {} cols",
-                            n_cols,
-                            row,
-                            line.len()
-                        ))];
-                    }
-                }
-                line.chars()
-                    .enumerate()
-                    .filter_map(|(col, point)| match point {
-                        '#' => Some(Ok(Point::new(col, row))),
-                        '.' => None,
-                        _ => Some(Err(format!(
-                            "Incorrect input: got '{}', expected only '.' or '#'",
-                            point
-                        ))),
-                    })
-                    .collect::<Vec<Result<_, String>>>()
+        let map = MapDisplay::<char>::from_str_strict(s)?.0;
+        let (n_cols, n_rows) = bounds(map.keys()).map_or((0, 0), |(_min, max)| {
+            (max.x as usize + 1, max.y as usize + 1)
+        });
+        let grid = map
+            .into_iter()
+            .map(|(coord, point)| match point {
+                '#' => Ok((coord, true)),
+                '.' => Ok((coord, false)),
+                _ => Err(format!(
+                    "Incorrect input: got '{}', expected only '.' or '#'",
+                    point
+                )),
             })
-            .collect::<Result<Vec<Point>, String>>();
+            .collect::<Result<HashMap<_, _>, _>>()?;
+        Self::from_grid(&grid, n_cols, n_rows)
+    }
+}
 
-        positions.map(|positions| Self {
+impl AsteroidMap {
+    /// The smallest map containing all these asteroids
+    fn from_coords(coords: &[Point]) -> Self {
+        let mut positions = coords.to_vec();
+        // Lines are built from sorted points, so `all_lines` relies on the positions being sorted
+        positions.sort();
+        positions.dedup();
+        Self {
+            n_cols: positions
+                .iter()
+                .map(|point| point.col + 1)
+                .max()
+                .unwrap_or(0),
+            n_rows: positions
+                .iter()
+                .map(|point| point.row + 1)
+                .max()
+                .unwrap_or(0),
+            positions,
+        }
+    }
+    /// A map of `n_cols` by `n_rows`, with asteroids on the coordinates set to true
+    fn from_grid(map: &HashMap<Coord, bool>, n_cols: usize, n_rows: usize) -> Result<Self, String> {
+        let positions = map
+            .iter()
+            .filter(|(_coord, is_asteroid)| **is_asteroid)
+            .map(|(coord, _is_asteroid)| {
+                let point = usize::try_from(coord.x)
+                    .and_then(|col| usize::try_from(coord.y).map(|row| Point::new(col, row)))
+                    .ok()
+                    .filter(|point| point.in_box(n_cols, n_rows));
+                point.ok_or_else(|| {
+                    format!(
+                        "Asteroid at {:?} is outside of the {}x{} map",
+                        coord, n_cols, n_rows
+                    )
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self {
             n_cols,
             n_rows,
-            positions,
+            ..Self::from_coords(&positions)
         })
     }
-}
-
-impl AsteroidMap {
     fn asteroids_line(&self, line: Line) -> impl Iterator<Item = Point> + '_ {
         line.points(self.n_cols, self.n_rows)
             .into_iter()
@@ -230,59 +287,82 @@ impl AsteroidMap {
             (*position, n_asteroids_seen)
         })
     }
+    /// The asteroids which can be seen from `origin`: the nearest one in each direction, where two
+    /// directions are the same if they reduce to the same one
+    fn visible_asteroids_from(&self, origin: Point) -> Vec<Point> {
+        let mut nearest = HashMap::new();
+        for position in self
+            .positions
+            .iter()
+            .filter(|position| **position != origin)
+        {
+            let dx = position.col as isize - origin.col as isize;
+            let dy = position.row as isize - origin.row as isize;
+            let divisor = gcd(dx.unsigned_abs(), dy.unsigned_abs()) as isize;
+            let closest = nearest
+                .entry((dx / divisor, dy / divisor))
+                .or_insert(*position);
+            if position.distance_squared(origin) < closest.distance_squared(origin) {
+                *closest = *position;
+            }
+        }
+        let mut visible = nearest.into_values().collect::<Vec<_>>();
+        visible.sort();
+        visible
+    }
+    /// How many other asteroids can be seen from `origin`
+    fn num_visible_from(&self, origin: Point) -> usize {
+        self.visible_asteroids_from(origin).len()
+    }
+    /// How many other asteroids can be seen from each asteroid
+    fn visibility_map(&self, line_of_sight: LineOfSight) -> HashMap<Point, usize> {
+        match line_of_sight {
+            LineOfSight::AllLines => self.n_asteroids_seen().collect(),
+            LineOfSight::ReducedDirections => self
+                .positions
+                .iter()
+                .map(|position| (*position, self.num_visible_from(*position)))
+                .collect(),
+        }
+    }
     fn most_asteroids_seen(&self) -> (Point, usize) {
-        self.n_asteroids_seen()
-            .max_by(|left, right| left.1.cmp(&right.1))
+        self.visibility_map(LineOfSight::ReducedDirections)
+            .into_iter()
+            // Break ties on the position so that the result doesn't depend on the hash order
+            .max_by_key(|(position, n_asteroids_seen)| (*n_asteroids_seen, *position))
             .unwrap()
     }
-    fn next_to_vaporize(
-        laser_index: usize,
-        line: &Vec<Point>,
-        vaporized: &mut HashSet<Point>,
-        section: GridSection,
-    ) -> Option<Point> {
-        // Because the line is sorted, points in the upper half section occur before points in the
-        // lower half
-        let increment = match section {
-            GridSection::UpperHalf => -1,
-            GridSection::LowerHalf => 1,
-        };
-        let mut asteroid_index = laser_index as isize + increment;
-        if asteroid_index < 0 {
-            return None;
-        }
-        while let Some(next) = line.get(asteroid_index as usize) {
-            if vaporized.contains(next) {
-                asteroid_index += increment;
-                if asteroid_index < 0 {
-                    return None;
-                }
-            } else {
-                vaporized.insert(*next);
-                return Some(*next);
-            }
-        }
-        None
+    /// The nearest asteroid on that side of the laser which is still there
+    fn next_to_vaporize(side: &[Point], vaporized: &mut HashSet<Point>) -> Option<Point> {
+        let next = *side.iter().find(|point| !vaporized.contains(point))?;
+        vaporized.insert(next);
+        Some(next)
     }
     fn vaporized(&self, laser: Point) -> impl Iterator<Item = Point> + '_ {
         let mut lines = self.all_lines().get_vec(&laser).unwrap().clone();
         // Sort lines by decreasing slopes
         lines.sort_by(|left, right| right.cmp_slopes(&left));
+        let n_lines = lines.len();
         let mut vaporized = HashSet::new();
         let mut section = GridSection::UpperHalf;
+        let mut misses = 0;
         // Once weve been from positive infinity to large negative slope, we'll go from infinity
         // again, but now looking at the other side of the line
         lines
             .into_iter()
             .map(move |line| {
                 let slope = line.slope;
-                let line = self.asteroids_line(line).collect::<Vec<_>>();
-                let laser_index = line.iter().position(|point| *point == laser).unwrap();
-
-                (slope, laser_index, line)
+                // Points sort by row first, so the ones before the laser are in the upper half
+                let (mut upper, mut lower): (Vec<_>, Vec<_>) = self
+                    .asteroids_line(line)
+                    .filter(|point| *point != laser)
+                    .partition(|point| *point < laser);
+                upper.sort_by_key(|point| point.distance_squared(laser));
+                lower.sort_by_key(|point| point.distance_squared(laser));
+                (slope, upper, lower)
             })
             .cycle()
-            .filter_map(move |(slope, laser_index, line)| {
+            .map(move |(slope, upper, lower)| {
                 // Note: I'm cheating by assuming that there is a horizontal line
                 // It's really iffy, but at this point I'm OK with whatever works in the one example :p
                 if slope.numer() == Some(&0) {
@@ -291,22 +371,76 @@ impl AsteroidMap {
                         GridSection::LowerHalf => GridSection::UpperHalf,
                     }
                 }
-                Self::next_to_vaporize(laser_index, &line, &mut vaporized, section)
+                let side = match section {
+                    GridSection::UpperHalf => &upper,
+                    GridSection::LowerHalf => &lower,
+                };
+                let next = Self::next_to_vaporize(side, &mut vaporized);
+                misses = if next.is_some() { 0 } else { misses + 1 };
+                (misses, next)
             })
+            // Each line is swept once per half, so a whole rotation without hitting anything means
+            // that there is nothing left to hit
+            .take_while(move |(misses, _)| *misses < 2 * n_lines)
+            .filter_map(|(_, next)| next)
+    }
+    /// When target gets vaporized, counting from 1. None if it isn't an asteroid or holds the laser
+    fn vaporization_order(&self, laser: Point, target: Point) -> Option<usize> {
+        if target == laser || !self.positions.contains(&target) {
+            return None;
+        }
+        self.vaporized(laser)
+            .position(|point| point == target)
+            .map(|index| index + 1)
     }
 }
 
+/// Time computing the visibility map with every method
+fn compare_lines_of_sight(asteroids: &AsteroidMap) {
+    let time = |line_of_sight| {
+        let now = Instant::now();
+        let visibility_map = asteroids.visibility_map(line_of_sight);
+        (visibility_map, now.elapsed())
+    };
+    let (all_lines, all_lines_time) = time(LineOfSight::AllLines);
+    let (reduced_directions, reduced_directions_time) = time(LineOfSight::ReducedDirections);
+    assert_eq!(all_lines, reduced_directions);
+    println!(
+        "visibility map from all lines: {:?}, from reduced directions: {:?}",
+        all_lines_time, reduced_directions_time
+    );
+}
+
+#[derive(Debug, StructOpt)]
+#[structopt(name = "asteroids", about = "Monitoring Station.")]
+struct Opt {
+    /// Time the visibility map with every way of finding lines of sight
+    #[structopt(long)]
+    bench: bool,
+}
+
 fn main() {
     let asteroids = AsteroidMap::from_str(include_str!("input.txt").trim()).unwrap();
     let best_asteroid = asteroids.most_asteroids_seen();
     let part_1 = best_asteroid.1;
     assert_eq!(326, part_1);
     println!("part 1: {}", part_1);
+    if Opt::from_args().bench {
+        compare_lines_of_sight(&asteroids);
+    }
     let laser_position = best_asteroid.0;
     let two_hundredth = asteroids.vaporized(laser_position).nth(199).unwrap();
     let part_2 = two_hundredth.col * 100 + two_hundredth.row;
     assert_eq!(1623, part_2);
-    println!("part 2: {}", part_2);
+    assert_eq!(
+        Some(200),
+        asteroids.vaporization_order(laser_position, two_hundredth)
+    );
+    println!(
+        "part 2: {} ({} away from the laser)",
+        part_2,
+        two_hundredth.manhattan_distance(laser_position)
+    );
 }
 
 #[cfg(test)]
@@ -339,6 +473,109 @@ mod tests {
         assert_eq!(expected, asteroids);
     }
     #[test]
+    fn test_line_points_near_the_edge() {
+        // Going down and left, the second step would be left of column 0
+        let line = Line::from_sorted_points(Point::new(2, 0), Point::new(0, 1));
+        assert_eq!(vec![Point::new(2, 0), Point::new(0, 1)], line.points(3, 3));
+    }
+    #[test]
+    fn test_distances() {
+        let (a, b) = (Point::new(1, 5), Point::new(4, 1));
+        assert_eq!(7, a.manhattan_distance(b));
+        assert_eq!(7, b.manhattan_distance(a));
+        assert_eq!(25, a.distance_squared(b));
+        assert_eq!(0, a.distance_squared(a));
+        let (far, origin) = (Point::new(usize::MAX, usize::MAX), Point::new(0, 0));
+        assert_eq!(
+            usize::MAX,
+            far.manhattan_distance(Point::new(0, usize::MAX))
+        );
+        assert_eq!(
+            1,
+            far.manhattan_distance(Point::new(usize::MAX, usize::MAX - 1))
+        );
+        assert_eq!(2, origin.distance_squared(Point::new(1, 1)));
+    }
+    #[test]
+    fn test_visible_asteroids_from() {
+        let asteroids = AsteroidMap::from_str(".#..#\n.....\n#####\n....#\n...##").unwrap();
+        let station = Point::new(3, 4);
+        let visible = asteroids.visible_asteroids_from(station);
+        let expected = [
+            (4, 0),
+            (0, 2),
+            (1, 2),
+            (2, 2),
+            (3, 2),
+            (4, 2),
+            (4, 3),
+            (4, 4),
+        ]
+        .iter()
+        .map(|coord| Point::new(coord.0, coord.1))
+        .collect::<Vec<_>>();
+        assert_eq!(expected, visible);
+        // (1, 0) hides behind (2, 2)
+        assert!(!visible.contains(&Point::new(1, 0)));
+        // The first sweep of the laser takes out exactly what the station sees
+        let mut first_sweep = asteroids
+            .vaporized(station)
+            .take(visible.len())
+            .collect::<Vec<_>>();
+        first_sweep.sort();
+        assert_eq!(visible, first_sweep);
+    }
+    #[test]
+    fn test_from_coords() {
+        let input = ".#..#\n.....\n#####\n....#\n...##";
+        let mut positions = AsteroidMap::from_str(input).unwrap().positions;
+        positions.reverse();
+        assert_eq!(
+            AsteroidMap::from_str(input).unwrap(),
+            AsteroidMap::from_coords(&positions)
+        );
+    }
+    #[test]
+    fn test_from_grid() {
+        let grid = [((1, 0), true), ((0, 1), true), ((1, 1), false)]
+            .iter()
+            .map(|((x, y), is_asteroid)| (Coord::new(*x, *y), *is_asteroid))
+            .collect();
+        assert_eq!(
+            Ok(AsteroidMap::from_str(".#.\n#..").unwrap()),
+            AsteroidMap::from_grid(&grid, 3, 2)
+        );
+        assert!(AsteroidMap::from_grid(&grid, 1, 2).is_err());
+    }
+    #[test]
+    fn test_visibility_map() {
+        let input = ".#..#\n.....\n#####\n....#\n...##";
+        let visibility_map = AsteroidMap::from_str(input)
+            .unwrap()
+            .visibility_map(LineOfSight::AllLines);
+        // .7..7
+        // .....
+        // 67775
+        // ....7
+        // ...87
+        let expected = [
+            ((1, 0), 7),
+            ((4, 0), 7),
+            ((0, 2), 6),
+            ((1, 2), 7),
+            ((2, 2), 7),
+            ((3, 2), 7),
+            ((4, 2), 5),
+            ((4, 3), 7),
+            ((3, 4), 8),
+            ((4, 4), 7),
+        ]
+        .iter()
+        .map(|((col, row), n)| (Point::new(*col, *row), *n))
+        .collect::<HashMap<_, _>>();
+        assert_eq!(expected, visibility_map);
+    }
+    #[test]
     fn test_most_asteroids_seen() {
         let input = ".#..#\n.....\n#####\n....#\n...##";
         let most_asteroids_seen = AsteroidMap::from_str(input)
@@ -413,6 +650,39 @@ mod tests {
 ###.##.####.##.#..##";
         let most_asteroids_seen = AsteroidMap::from_str(input).unwrap().most_asteroids_seen();
         assert_eq!(210, most_asteroids_seen.1);
+        let asteroids = AsteroidMap::from_str(input).unwrap();
+        assert_eq!(
+            asteroids.visibility_map(LineOfSight::AllLines),
+            asteroids.visibility_map(LineOfSight::ReducedDirections)
+        );
+    }
+    /// Run with `cargo test --release -- --ignored` to time both ways on the 20x20 example
+    #[test]
+    #[ignore]
+    fn bench_lines_of_sight() {
+        let input = ".#..##.###...#######
+##.############..##.
+.#.######.########.#
+.###.#######.####.#.
+#####.##.#.##.###.##
+..#####..#.#########
+####################
+#.####....###.#.#.##
+##.#################
+#####.##.###..####..
+..######..##.#######
+####.##.####...##..#
+.#####..#.######.###
+##...#.##########...
+#.##########.#######
+.####.#.###.###.#.##
+....##.##.###..#####
+.#.#.###########.###
+#.#.#.#####.####.###
+###.##.####.##.#..##";
+        let asteroids = AsteroidMap::from_str(input).unwrap();
+        assert_eq!(210, asteroids.most_asteroids_seen().1);
+        compare_lines_of_sight(&asteroids);
     }
     #[test]
     fn test_nth_vaporized() {
@@ -450,5 +720,28 @@ mod tests {
         assert_eq!(Point::new(8, 2), vaporized[199]);
         assert_eq!(Point::new(10, 9), vaporized[200]);
         assert_eq!(Point::new(11, 1), vaporized[298]);
+        assert_eq!(299, asteroids.vaporized(laser).count());
+        assert_eq!(
+            Some(1),
+            asteroids.vaporization_order(laser, Point::new(11, 12))
+        );
+        assert_eq!(
+            Some(200),
+            asteroids.vaporization_order(laser, Point::new(8, 2))
+        );
+        assert_eq!(
+            Some(299),
+            asteroids.vaporization_order(laser, Point::new(11, 1))
+        );
+        assert_eq!(None, asteroids.vaporization_order(laser, laser));
+        assert_eq!(None, asteroids.vaporization_order(laser, Point::new(0, 0)));
+    }
+    #[test]
+    fn test_vaporization_without_horizontal_line() {
+        let asteroids = AsteroidMap::from_str(".#.\n#.#").unwrap();
+        let laser = Point::new(1, 0);
+        assert_eq!(0, asteroids.vaporized(laser).count());
+        assert_eq!(None, asteroids.vaporization_order(laser, Point::new(0, 1)));
+        assert_eq!(None, asteroids.vaporization_order(laser, Point::new(2, 1)));
     }
 }
@@ -1,9 +1,9 @@
 #![deny(warnings)]
 
 use direction::{CardinalDirection, Coord};
-use intcode_computer::{ComputationStatus, Computer};
-use map_display::MapDisplay;
-use std::collections::HashMap;
+use intcode_computer::{Computer, IntcodeRobot};
+use map_display::Grid;
+use std::convert::TryFrom;
 use std::fmt::{self, Display, Formatter};
 use std::str::FromStr;
 
@@ -19,22 +19,22 @@ impl Default for Color {
     }
 }
 
-impl FromStr for Color {
-    type Err = String;
-    fn from_str(x: &str) -> Result<Self, Self::Err> {
+impl TryFrom<isize> for Color {
+    type Error = String;
+    fn try_from(x: isize) -> Result<Self, Self::Error> {
         match x {
-            "0" => Ok(Self::Black),
-            "1" => Ok(Self::White),
+            0 => Ok(Self::Black),
+            1 => Ok(Self::White),
             _ => Err(format!("Can't construct Color from {}", x)),
         }
     }
 }
 
-impl Into<&'static str> for Color {
-    fn into(self) -> &'static str {
+impl Into<isize> for Color {
+    fn into(self) -> isize {
         match self {
-            Self::Black => "0",
-            Self::White => "1",
+            Self::Black => 0,
+            Self::White => 1,
         }
     }
 }
@@ -55,29 +55,29 @@ enum Turn {
     Right,
 }
 
-impl FromStr for Turn {
-    type Err = String;
-    fn from_str(x: &str) -> Result<Self, Self::Err> {
+impl TryFrom<isize> for Turn {
+    type Error = String;
+    fn try_from(x: isize) -> Result<Self, Self::Error> {
         match x {
-            "0" => Ok(Self::Left),
-            "1" => Ok(Self::Right),
+            0 => Ok(Self::Left),
+            1 => Ok(Self::Right),
             _ => Err(format!("Can't construct Turn from {}", x)),
         }
     }
 }
 
 struct Robot {
-    brain: Computer,
-    map: HashMap<Coord, Color>,
+    brain: IntcodeRobot,
+    map: Grid<Color>,
     position: Coord,
     direction: CardinalDirection,
 }
 
 impl Robot {
-    fn new(brain: Computer, initial_cell: Option<Color>) -> Self {
-        let mut map = HashMap::new();
+    fn new(brain: IntcodeRobot, initial_cell: Option<Color>) -> Self {
+        let mut map = Grid::new();
         if let Some(color) = initial_cell {
-            map.insert(Coord::default(), color);
+            map.set(Coord::default(), color);
         }
         Self {
             brain,
@@ -88,12 +88,12 @@ impl Robot {
     }
     fn current_color(&self) -> Color {
         self.map
-            .get(&self.position)
+            .get(self.position)
             .map(Color::clone)
             .unwrap_or(Color::default())
     }
     fn paint_current_location(&mut self, color: Color) {
-        self.map.insert(self.position, color);
+        self.map.set(self.position, color);
     }
     fn turn_and_walk_away(&mut self, turn: Turn) {
         self.direction = match turn {
@@ -102,24 +102,24 @@ impl Robot {
         };
         self.position = self.position + self.direction.coord();
     }
+    /// Tell the brain the color under the robot, and wait for it to answer with the color to
+    /// paint and where to turn, until it halts
     fn walk(&mut self) {
-        let mut status = ComputationStatus::StarvingForMockInput;
-        while status != ComputationStatus::Done {
-            let input = self.current_color().into();
-            self.brain.set_mock_io_input(input);
-            status = self.brain.compute().unwrap();
-            let output = self.brain.get_mock_io_output().unwrap();
-            let outputs = output.split("\n").collect::<Vec<_>>();
-            let color: Color = outputs[0].parse().unwrap();
-            let turn: Turn = outputs[1].parse().unwrap();
-            self.paint_current_location(color);
-            self.turn_and_walk_away(turn);
+        loop {
+            match self.brain.tick(self.current_color().into(), 2).unwrap()[..] {
+                [color, turn] => {
+                    self.paint_current_location(Color::try_from(color).unwrap());
+                    self.turn_and_walk_away(Turn::try_from(turn).unwrap());
+                }
+                [] => break,
+                _ => panic!("The brain halted between painting and turning"),
+            }
         }
     }
 }
 
 fn main() {
-    let brain = Computer::from_str(include_str!("input.txt")).unwrap();
+    let brain = IntcodeRobot::new(Computer::from_str(include_str!("input.txt")).unwrap());
     {
         let mut beebop = Robot::new(brain.clone(), None);
         beebop.walk();
@@ -130,7 +130,7 @@ fn main() {
     {
         let mut beebop = Robot::new(brain, Some(Color::White));
         beebop.walk();
-        let part_2 = format!("{}", MapDisplay(beebop.map));
+        let part_2 = format!("{}", beebop.map);
         assert_eq!(
             "██░░████████░░░░░░████░░░░░░░░██░░░░░░░░████░░░░██████░░░░████░░░░░░░░██░░░░░░░░██████\r
 ██░░████████░░████░░████████░░██░░████████░░████░░██░░████░░██░░████████░░████████████\r
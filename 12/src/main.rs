@@ -1,6 +1,7 @@
 #![deny(warnings)]
 
 use primes::PrimeSet;
+use simulation::find_cycle;
 use std::collections::BTreeMap;
 use std::fmt;
 use std::ops::Add;
@@ -69,6 +70,23 @@ impl fmt::Debug for Moon {
     }
 }
 
+/// As printed in the puzzle: `pos=<x= 2, y=-1, z= 1>, vel=<x= 3, y=-1, z=-1>`
+impl fmt::Display for Moon {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (p, v) = (self.position, self.velocity);
+        write!(
+            f,
+            "pos=<x={:2}, y={:2}, z={:2}>, vel=<x={:2}, y={:2}, z={:2}>",
+            p.x(),
+            p.y(),
+            p.z(),
+            v.x(),
+            v.y(),
+            v.z()
+        )
+    }
+}
+
 impl Moon {
     fn still(pos: &(isize, isize, isize)) -> Self {
         Self::moving(pos, &(0, 0, 0))
@@ -97,6 +115,15 @@ impl Moon {
                 .collect::<Vec<_>>(),
         )
     }
+    /// This moon with its velocity changed by the gravity of all these moons
+    fn pulled_by<'a>(&self, moons: impl Iterator<Item = &'a Moon>) -> Self {
+        Self {
+            position: self.position,
+            velocity: moons
+                .map(|other_moon| self.velocity_change(other_moon))
+                .fold(self.velocity, |a, b| a + b),
+        }
+    }
     fn apply_velocity(&self) -> Self {
         Self {
             position: self.position + self.velocity,
@@ -129,6 +156,16 @@ impl fmt::Debug for Moons {
     }
 }
 
+/// One moon per line, ordered by name
+impl fmt::Display for Moons {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for moon in self.moons.values() {
+            writeln!(f, "{}", moon)?;
+        }
+        Ok(())
+    }
+}
+
 impl Moons {
     fn new_still(moons: &[(&'static str, (isize, isize, isize))]) -> Self {
         Self {
@@ -147,26 +184,38 @@ impl Moons {
                 .collect(),
         }
     }
+    /// Advance by one step in place, without rebuilding the map of moons
+    fn step_mut(&mut self) {
+        let before = self.moons.values().cloned().collect::<Vec<_>>();
+        for moon in self.moons.values_mut() {
+            *moon = moon.pulled_by(before.iter()).apply_velocity();
+        }
+    }
+    fn total_energy(&self) -> isize {
+        self.moons.values().map(Moon::total_energy).sum()
+    }
+    fn pos_and_vel_on_axis(&self, axis: usize) -> Vec<(isize, isize)> {
+        self.moons
+            .values()
+            .map(|moon| {
+                (
+                    moon.position.iter().nth(axis).unwrap(),
+                    moon.velocity.iter().nth(axis).unwrap(),
+                )
+            })
+            .collect()
+    }
+}
+
+/// One copy of the moons per stage of a step, to check `step_mut` against
+#[cfg(test)]
+impl Moons {
     fn apply_gravity(&self) -> Self {
         Self {
             moons: self
                 .moons
                 .iter()
-                .map(|(name, moon)| {
-                    let velocity = self
-                        .moons
-                        .clone()
-                        .values()
-                        .map(|other_moon| moon.velocity_change(other_moon))
-                        .fold(moon.velocity, |a, b| a + b);
-                    (
-                        *name,
-                        Moon {
-                            position: moon.position,
-                            velocity: velocity,
-                        },
-                    )
-                })
+                .map(|(name, moon)| (*name, moon.pulled_by(self.moons.values())))
                 .collect(),
         }
     }
@@ -182,20 +231,22 @@ impl Moons {
     fn simulate_motion_for_one_step(&self) -> Self {
         self.apply_gravity().apply_velocity()
     }
-    fn total_energy(&self) -> isize {
-        self.moons.values().map(Moon::total_energy).sum()
-    }
-    fn pos_and_vel_on_axis(&self, axis: usize) -> Vec<(isize, isize)> {
-        self.moons
-            .values()
-            .map(|moon| {
-                (
-                    moon.position.iter().nth(axis).unwrap(),
-                    moon.velocity.iter().nth(axis).unwrap(),
-                )
-            })
-            .collect()
-    }
+}
+
+/// Axes don't interact, so each one can be simulated on its own: apply gravity then velocity to
+/// the (position, velocity) of every moon
+fn step_axis(state: &[(isize, isize)]) -> Vec<(isize, isize)> {
+    state
+        .iter()
+        .map(|(position, velocity)| {
+            let velocity = velocity
+                + state
+                    .iter()
+                    .map(|(other, _velocity)| (other - position).signum())
+                    .sum::<isize>();
+            (position + velocity, velocity)
+        })
+        .collect()
 }
 
 #[derive(Clone)]
@@ -211,12 +262,15 @@ impl Simulation {
             initial: moons,
         }
     }
+    /// The total energy of the system after each step
+    fn energies(self) -> impl Iterator<Item = isize> {
+        self.map(|moons| moons.total_energy())
+    }
     fn detect_period_on_axis(&mut self, axis: usize) -> usize {
-        let initial = self.initial.pos_and_vel_on_axis(axis);
-        self.clone()
-            .take_while(|next| next.pos_and_vel_on_axis(axis) != initial)
-            .count()
-            + 1
+        let (_mu, lambda) = find_cycle(self.initial.pos_and_vel_on_axis(axis), |state| {
+            step_axis(state)
+        });
+        lambda
     }
     fn detect_period(&mut self) -> usize {
         let mut prime_set = PrimeSet::new();
@@ -254,7 +308,7 @@ impl Iterator for Simulation {
     type Item = Moons;
 
     fn next(&mut self) -> Option<Moons> {
-        self.moons = self.moons.simulate_motion_for_one_step();
+        self.moons.step_mut();
         Some(self.moons.clone())
     }
 }
@@ -267,8 +321,8 @@ fn main() {
         ("Callisto", (19, 11, 9)),
     ]);
     {
-        let mut simulation = Simulation::new(initial_moons.clone());
-        let part_1 = simulation.nth(999).unwrap().total_energy();
+        let simulation = Simulation::new(initial_moons.clone());
+        let part_1 = simulation.energies().nth(999).unwrap();
         assert_eq!(9441, part_1);
         println!("part 1: {}", part_1);
     }
@@ -357,6 +411,25 @@ mod tests {
         assert_eq!(evolving_moons, simulation.take(10).collect::<Vec<_>>());
     }
     #[test]
+    fn test_display() {
+        let moons = Moons::new_moving(&[
+            ("Io", (2, -1, 1), (3, -1, -1)),
+            ("Europa", (3, -7, -4), (1, 3, 3)),
+            ("Ganymede", (-8, -10, 0), (0, 0, 0)),
+        ]);
+        assert_eq!(
+            "pos=<x= 2, y=-1, z= 1>, vel=<x= 3, y=-1, z=-1>",
+            moons.moons["Io"].to_string()
+        );
+        assert_eq!(
+            "pos=<x= 3, y=-7, z=-4>, vel=<x= 1, y= 3, z= 3>
+pos=<x=-8, y=-10, z= 0>, vel=<x= 0, y= 0, z= 0>
+pos=<x= 2, y=-1, z= 1>, vel=<x= 3, y=-1, z=-1>
+",
+            moons.to_string()
+        );
+    }
+    #[test]
     fn test_total_energy() {
         let initial_moons = Moons::new_still(&[
             ("Io", (-1, -0, 2)),
@@ -364,8 +437,41 @@ mod tests {
             ("Ganymede", (4, -8, 8)),
             ("Callisto", (3, 5, -1)),
         ]);
-        let mut simulation = Simulation::new(initial_moons);
+        let mut simulation = Simulation::new(initial_moons.clone());
         assert_eq!(179, simulation.nth(9).unwrap().total_energy());
+        let energies = Simulation::new(initial_moons).energies().take(10);
+        assert_eq!(Some(179), energies.last());
+    }
+    #[test]
+    fn test_step_mut() {
+        let mut moons = Moons::new_still(&[
+            ("Io", (-8, -10, 0)),
+            ("Europa", (5, 5, 10)),
+            ("Ganymede", (2, -7, 3)),
+            ("Callisto", (9, -8, -3)),
+        ]);
+        for _ in 0..100 {
+            let expected = moons.simulate_motion_for_one_step();
+            moons.step_mut();
+            assert_eq!(expected, moons);
+        }
+        assert_eq!(1940, moons.total_energy());
+    }
+    #[test]
+    fn test_step_axis() {
+        let initial_moons = Moons::new_still(&[
+            ("Io", (-1, -0, 2)),
+            ("Europa", (2, -10, -7)),
+            ("Ganymede", (4, -8, 8)),
+            ("Callisto", (3, 5, -1)),
+        ]);
+        let next = initial_moons.simulate_motion_for_one_step();
+        for axis in 0..3 {
+            assert_eq!(
+                next.pos_and_vel_on_axis(axis),
+                step_axis(&initial_moons.pos_and_vel_on_axis(axis))
+            );
+        }
     }
     #[test]
     fn test_detect_period() {
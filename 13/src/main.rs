@@ -1,12 +1,13 @@
 #![deny(warnings)]
 
 use direction::Coord;
-use intcode_computer::{ComputationStatus, Computer};
-use map_display::MapDisplay;
-use std::collections::HashMap;
+use intcode_computer::{ComputationStatus, Computer, StdIo};
+use map_display::Grid;
+use std::convert::TryFrom;
 use std::fmt::{self, Display, Formatter};
 use std::io::{stdout, Write};
 use std::str::FromStr;
+use std::time::Instant;
 use structopt::StructOpt;
 use termion::event::{Event, Key};
 use termion::input::TermRead;
@@ -40,15 +41,15 @@ impl Display for TileContent {
     }
 }
 
-impl FromStr for TileContent {
-    type Err = String;
-    fn from_str(x: &str) -> Result<Self, Self::Err> {
+impl TryFrom<isize> for TileContent {
+    type Error = String;
+    fn try_from(x: isize) -> Result<Self, Self::Error> {
         match x {
-            "0" => Ok(Self::Empty),
-            "1" => Ok(Self::Wall),
-            "2" => Ok(Self::Block),
-            "3" => Ok(Self::Paddle),
-            "4" => Ok(Self::Ball),
+            0 => Ok(Self::Empty),
+            1 => Ok(Self::Wall),
+            2 => Ok(Self::Block),
+            3 => Ok(Self::Paddle),
+            4 => Ok(Self::Ball),
             _ => Err(format!("Can't construct TileContent from {}", x)),
         }
     }
@@ -57,13 +58,16 @@ impl FromStr for TileContent {
 #[derive(Clone)]
 struct Arcade {
     computer: Computer,
-    screen: HashMap<Coord, TileContent>,
+    screen: Grid<TileContent>,
     score: isize,
+    status: ComputationStatus,
+    /// Where the ball was last time the predictive autoplay looked at it
+    previous_ball: Option<Coord>,
 }
 
 impl Display for Arcade {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", MapDisplay(self.screen.clone()))
+        write!(f, "{}", self.screen)
     }
 }
 
@@ -71,8 +75,10 @@ impl Arcade {
     fn new(computer: Computer) -> Self {
         Self {
             computer,
-            screen: HashMap::new(),
+            screen: Grid::new(),
             score: 0,
+            status: ComputationStatus::default(),
+            previous_ball: None,
         }
     }
     fn new_game(mut computer: Computer) -> Self {
@@ -80,44 +86,117 @@ impl Arcade {
         Self::new(computer)
     }
     fn compute(&mut self, input: isize) -> ComputationStatus {
-        self.computer.set_mock_io_input(&format!("{}", input));
+        self.computer
+            .set_mock_io_input(&format!("{}", input))
+            .unwrap();
         let status = self.computer.compute().unwrap();
-        let output = self.computer.get_mock_io_output().unwrap();
-        let lines = output.split("\n").collect::<Vec<_>>();
-        for pixel in lines.chunks(3) {
-            if pixel.iter().count() != 3 {
-                break;
-            }
+        let (tiles, score) = self.drain_tiles();
+        for (point, content) in tiles {
+            self.screen.set(point, content);
+        }
+        if let Some(score) = score {
+            self.score = score;
+        }
+        self.status = status;
+        status
+    }
+    /// Decode what the program drew since we last looked: the tiles in order, and the last score
+    /// it displayed if any
+    fn drain_tiles(&mut self) -> (Vec<(Coord, TileContent)>, Option<isize>) {
+        let mut tiles = vec![];
+        let mut score = None;
+        for pixel in self.computer.drain_outputs().chunks_exact(3) {
             let point = Coord {
-                x: pixel[0].trim().parse().unwrap(),
-                y: pixel[1].trim().parse().unwrap(),
+                x: pixel[0] as i32,
+                y: pixel[1] as i32,
             };
             if point == (Coord { x: -1, y: 0 }) {
-                self.score = pixel[2].trim().parse().unwrap();
+                score = Some(pixel[2]);
             } else {
-                let content = TileContent::from_str(pixel[2].trim()).unwrap();
-                self.screen.insert(point, content);
+                tiles.push((point, TileContent::try_from(pixel[2]).unwrap()));
             }
         }
-        status
+        (tiles, score)
     }
-    fn find_x_position(&self, tile: &TileContent) -> i32 {
+    fn block_count(&self) -> usize {
+        self.screen
+            .iter()
+            .filter(|(_point, tile)| **tile == TileContent::Block)
+            .count()
+    }
+    /// Either all blocks were broken or the game stopped. Nothing is drawn on a new arcade until
+    /// it computes, so it isn't over before it began
+    fn is_over(&self) -> bool {
+        self.status.is_done() || (!self.screen.is_empty() && self.block_count() == 0)
+    }
+    /// None until the tile was drawn
+    fn find_position(&self, tile: &TileContent) -> Option<Coord> {
         self.screen
             .iter()
             .find(|(_point, content)| *content == tile)
-            .unwrap()
-            .0
-            .x
+            .map(|(point, _content)| *point)
+    }
+    fn ball_x(&self) -> Option<i32> {
+        self.find_position(&TileContent::Ball).map(|ball| ball.x)
+    }
+    fn paddle_x(&self) -> Option<i32> {
+        self.find_position(&TileContent::Paddle)
+            .map(|paddle| paddle.x)
+    }
+    /// Move the paddle towards the ball. Stay put until both were drawn
+    fn suggested_joystick(&self) -> isize {
+        match (self.ball_x(), self.paddle_x()) {
+            (Some(ball), Some(paddle)) => (ball - paddle).signum() as isize,
+            _ => 0,
+        }
     }
     fn autoplay(&mut self) -> ComputationStatus {
-        let joystick = if self.find_x_position(&TileContent::Ball)
-            < self.find_x_position(&TileContent::Paddle)
-        {
-            -1
-        } else {
-            1
+        self.compute(self.suggested_joystick())
+    }
+    /// Where the ball would cross the paddle's row, assuming it keeps going in a straight line and
+    /// bounces off the side walls.
+    /// None if it's going up or could still bounce off a block, and in the last couple of rows
+    /// where we follow the ball so that it hits the paddle the same way as with plain tracking
+    fn predict_landing_x(&self, ball: Coord, previous_ball: Coord, paddle: Coord) -> Option<i32> {
+        let velocity = ball - previous_ball;
+        let lowest_block = self
+            .screen
+            .iter()
+            .filter(|(_point, tile)| **tile == TileContent::Block)
+            .map(|(point, _tile)| point.y)
+            .max()
+            .unwrap_or(0);
+        if velocity.y <= 0 || ball.y <= lowest_block || ball.y >= paddle.y - 2 {
+            return None;
+        }
+        let (_min, max) = self.screen.bounds()?;
+        // The walls are on the left and right edges of the screen
+        let (left, right) = (1, max.x - 1);
+        let steps = (paddle.y - ball.y) / velocity.y;
+        let x = ball.x + velocity.x * steps - left;
+        // Bouncing off the walls folds the trajectory back into the playing field
+        let period = 2 * (right - left);
+        if period == 0 {
+            return Some(left);
+        }
+        let x = x.rem_euclid(period);
+        Some(left + if x > right - left { period - x } else { x })
+    }
+    /// Move the paddle towards where the ball is going to land rather than where it currently is
+    fn autoplay_predictive(&mut self) -> ComputationStatus {
+        let (ball, paddle) = match (
+            self.find_position(&TileContent::Ball),
+            self.find_position(&TileContent::Paddle),
+        ) {
+            (Some(ball), Some(paddle)) => (ball, paddle),
+            _ => return self.compute(0),
         };
-        self.compute(joystick)
+        let target = self
+            .previous_ball
+            .and_then(|previous_ball| self.predict_landing_x(ball, previous_ball, paddle))
+            .unwrap_or(ball.x);
+        self.previous_ball = Some(ball);
+        self.compute((target - paddle.x).signum() as isize)
     }
 }
 
@@ -139,6 +218,44 @@ fn display_arcade(stdout: &mut dyn Write, arcade: &Arcade) {
 struct Opt {
     #[structopt(short, long)]
     play: bool,
+    /// Anticipate where the ball lands when autoplaying with the space bar
+    #[structopt(long)]
+    predictive: bool,
+    /// Autoplay a whole game without rendering it, and report how fast it ran
+    #[structopt(long)]
+    bench: bool,
+    /// Run the bare program on stdin: type each joystick position (-1, 0 or 1) when prompted, and
+    /// read its raw outputs
+    #[structopt(long)]
+    stdin: bool,
+}
+
+/// Time a whole game of autoplay: each step runs the arcade program until it needs the joystick
+fn bench(program: Computer) {
+    let start = Instant::now();
+    let mut arcade = Arcade::new_game(program);
+    arcade.compute(0);
+    let mut steps = 1;
+    while !arcade.is_over() {
+        arcade.autoplay();
+        steps += 1;
+    }
+    let elapsed = start.elapsed();
+    assert_eq!(12954, arcade.score);
+    println!(
+        "bench: {} steps in {}ms ({:.0} steps/s), score: {}",
+        steps,
+        elapsed.as_millis(),
+        steps as f64 / elapsed.as_secs_f64(),
+        arcade.score
+    );
+}
+
+/// The only computer reading from stdin, which blocks until the user answers
+fn play_on_stdin(program: Computer) -> Result<ComputationStatus, String> {
+    let mut computer = program;
+    computer.data[0] = 2;
+    computer.with_io(StdIo).compute()
 }
 
 fn main() {
@@ -147,25 +264,28 @@ fn main() {
         let mut arcade = Arcade::new(program.clone());
         arcade.compute(0);
         println!("{}", arcade);
-        let part_1 = arcade
-            .screen
-            .values()
-            .filter(|tile| **tile == TileContent::Block)
-            .count();
+        let part_1 = arcade.block_count();
         assert_eq!(247, part_1);
         println!("part 1: {}", part_1);
     }
     {
         let mut arcade = Arcade::new_game(program.clone());
-        let mut status = arcade.compute(0);
-        while status != ComputationStatus::Done {
-            status = arcade.autoplay();
+        arcade.compute(0);
+        while !arcade.is_over() {
+            arcade.autoplay();
         }
         let part_2 = arcade.score;
         assert_eq!(12954, part_2);
         println!("part 2: {}", part_2);
     }
     let opt = Opt::from_args();
+    if opt.bench {
+        bench(program.clone());
+    }
+    if opt.stdin {
+        let status = play_on_stdin(program.clone()).unwrap();
+        println!("The arcade is {}", status);
+    }
     if opt.play {
         let mut arcade = Arcade::new_game(program.clone());
 
@@ -173,26 +293,30 @@ fn main() {
         let mut stdout = stdout().into_raw_mode().unwrap();
 
         let mut joystick = 0;
-        let mut status = arcade.compute(joystick);
+        arcade.compute(joystick);
         display_arcade(&mut stdout, &arcade);
-        while status != ComputationStatus::Done {
+        while !arcade.is_over() {
             if let Some(evt) = stdin.next() {
                 match evt.unwrap() {
                     Event::Key(Key::Char('q')) => {
                         break;
                     }
                     Event::Key(Key::Char(' ')) => {
-                        status = arcade.autoplay();
+                        if opt.predictive {
+                            arcade.autoplay_predictive();
+                        } else {
+                            arcade.autoplay();
+                        }
                         display_arcade(&mut stdout, &arcade);
                     }
-                    Event::Key(Key::Char('j')) => {
+                    Event::Key(Key::Char('j')) | Event::Key(Key::Left) => {
                         joystick = -1;
-                        status = arcade.compute(joystick);
+                        arcade.compute(joystick);
                         display_arcade(&mut stdout, &arcade);
                     }
-                    Event::Key(Key::Char('k')) => {
+                    Event::Key(Key::Char('k')) | Event::Key(Key::Right) => {
                         joystick = 1;
-                        status = arcade.compute(joystick);
+                        arcade.compute(joystick);
                         display_arcade(&mut stdout, &arcade);
                     }
                     _ => {
@@ -202,5 +326,75 @@ fn main() {
             }
         }
         display_arcade(&mut stdout, &arcade);
+        if arcade.is_over() {
+            let message = if arcade.block_count() == 0 {
+                "You win!"
+            } else {
+                "Game over"
+            };
+            write!(stdout, "{}\r\n", message).unwrap();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_autoplay_breaks_all_blocks() {
+        let program = Computer::from_str(include_str!("input.txt")).unwrap();
+        let mut arcade = Arcade::new_game(program);
+        assert!(!arcade.is_over());
+        arcade.compute(0);
+        assert_eq!(247, arcade.block_count());
+        while !arcade.is_over() {
+            arcade.autoplay();
+        }
+        assert_eq!(0, arcade.block_count());
+        assert_eq!(12954, arcade.score);
+    }
+    #[test]
+    fn test_drain_tiles() {
+        let program = Computer::from_str(
+            "104,1,104,2,104,3,104,-1,104,0,104,42,104,6,104,5,104,4,104,-1,104,0,104,43,99",
+        )
+        .unwrap();
+        let mut arcade = Arcade::new(program);
+        arcade.computer.compute().unwrap();
+        let (tiles, score) = arcade.drain_tiles();
+        assert_eq!(
+            vec![
+                (Coord { x: 1, y: 2 }, TileContent::Paddle),
+                (Coord { x: 6, y: 5 }, TileContent::Ball)
+            ],
+            tiles
+        );
+        assert_eq!(Some(43), score);
+        assert_eq!((vec![], None), arcade.drain_tiles());
+    }
+    #[test]
+    fn test_suggested_joystick() {
+        let program = Computer::from_str(include_str!("input.txt")).unwrap();
+        let mut arcade = Arcade::new_game(program);
+        assert_eq!(None, arcade.ball_x());
+        assert_eq!(None, arcade.paddle_x());
+        assert_eq!(0, arcade.suggested_joystick());
+        arcade.compute(0);
+        let (ball, paddle) = (arcade.ball_x().unwrap(), arcade.paddle_x().unwrap());
+        assert_eq!(
+            (ball - paddle).signum() as isize,
+            arcade.suggested_joystick()
+        );
+    }
+    #[test]
+    fn test_predictive_autoplay_breaks_all_blocks() {
+        let program = Computer::from_str(include_str!("input.txt")).unwrap();
+        let mut arcade = Arcade::new_game(program);
+        arcade.compute(0);
+        while !arcade.is_over() {
+            arcade.autoplay_predictive();
+        }
+        assert_eq!(0, arcade.block_count());
+        assert_eq!(12954, arcade.score);
     }
 }
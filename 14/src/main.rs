@@ -4,7 +4,7 @@ use itertools::Itertools;
 use petgraph::{
     dot::Dot,
     graph::{DiGraph, NodeIndex},
-    visit::{Reversed, Topo},
+    visit::{EdgeRef, Reversed, Topo},
     Direction,
 };
 use std::collections::HashMap;
@@ -108,6 +108,18 @@ impl FromStr for Nanofactory {
             .split('\n')
             .map(Reaction::from_str)
             .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self::from_reactions(reactions))
+    }
+}
+
+impl Debug for Nanofactory {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", Dot::new(&self.graph))
+    }
+}
+
+impl Nanofactory {
+    fn from_reactions(reactions: Vec<Reaction>) -> Self {
         let nodes = std::iter::once(Chemical {
             id: "ORE".to_string(),
             quantity: 1,
@@ -135,17 +147,36 @@ impl FromStr for Nanofactory {
         for (vertex, name) in graph.node_weights_mut().zip(nodes.iter()) {
             *vertex = name.clone();
         }
-        Ok(Self { graph })
+        Self { graph }
     }
-}
-
-impl Debug for Nanofactory {
-    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        write!(f, "{:?}", Dot::new(&self.graph))
+    /// The reactions this factory was built from, with reactants in their original order
+    fn reactions(&self) -> Vec<Reaction> {
+        self.graph
+            .node_indices()
+            .filter_map(|product| {
+                let mut inputs = self
+                    .graph
+                    .edges_directed(product, Direction::Incoming)
+                    .collect::<Vec<_>>();
+                if inputs.is_empty() {
+                    // ORE isn't produced by any reaction
+                    return None;
+                }
+                inputs.sort_by_key(|edge| edge.id());
+                let reactants = inputs
+                    .into_iter()
+                    .map(|edge| Chemical {
+                        id: self.product(edge.source()).id,
+                        quantity: *edge.weight(),
+                    })
+                    .collect();
+                Some(Reaction {
+                    reactants,
+                    product: self.product(product),
+                })
+            })
+            .collect()
     }
-}
-
-impl Nanofactory {
     fn num_ore_needed_for_fuel(&self, n_needed: usize) -> usize {
         // For each chemical, how many times must I run the reaction which produces it
         let mut product_needed = HashMap::new();
@@ -225,6 +256,8 @@ fn main() {
     let part_1 = factory.num_ore_needed_for_fuel(1);
     assert_eq!(378929, part_1);
     println!("part 1: {}", part_1);
+    let rebuilt = Nanofactory::from_reactions(factory.reactions());
+    assert_eq!(part_1, rebuilt.num_ore_needed_for_fuel(1));
     let part_2 = factory.num_fuel_produced_by_one_trillion_ore();
     println!("part 2: {}", part_2);
 }
@@ -232,14 +265,85 @@ fn main() {
 #[cfg(test)]
 mod tests {
     use super::*;
+    /// The binary search for part 2 relies on needing at least as much ore for more fuel
+    fn assert_ore_is_monotonic(factory: &Nanofactory, fuel: std::ops::Range<usize>) {
+        let ore = fuel
+            .map(|fuel| (fuel, factory.num_ore_needed_for_fuel(fuel)))
+            .collect::<Vec<_>>();
+        for pair in ore.windows(2) {
+            assert!(
+                pair[0].1 <= pair[1].1,
+                "{} fuel needs {} ore but {} fuel needs {}",
+                pair[0].0,
+                pair[0].1,
+                pair[1].0,
+                pair[1].1
+            );
+        }
+    }
     fn test_num_ore(input: &str, expected: usize, expected_trillion_ore: usize) {
         let factory = Nanofactory::from_str(input).unwrap();
+        assert_ore_is_monotonic(&factory, 1..200);
         let num_ore = factory.num_ore_needed_for_fuel(1);
         assert_eq!(expected, num_ore);
         let num_fuel = factory.num_fuel_produced_by_one_trillion_ore();
         assert_eq!(expected_trillion_ore, num_fuel);
     }
     #[test]
+    fn test_reactions_round_trip() {
+        let input = "9 ORE => 2 A
+8 ORE => 3 B
+7 ORE => 5 C
+3 A, 4 B => 1 AB
+5 B, 7 C => 1 BC
+4 C, 1 A => 1 CA
+2 AB, 3 BC, 4 CA => 1 FUEL";
+        let factory = Nanofactory::from_str(input).unwrap();
+        let reactions = factory
+            .reactions()
+            .iter()
+            .map(|reaction| format!("{:?}", reaction))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                "(9, ORE) => (2, A)",
+                "(8, ORE) => (3, B)",
+                "(7, ORE) => (5, C)",
+                "(3, A), (4, B) => (1, AB)",
+                "(5, B), (7, C) => (1, BC)",
+                "(4, C), (1, A) => (1, CA)",
+                "(2, AB), (3, BC), (4, CA) => (1, FUEL)",
+            ],
+            reactions
+        );
+    }
+    #[test]
+    fn test_num_ore_is_monotonic_in_fuel() {
+        let chemical = |quantity, id: &str| Chemical {
+            id: id.to_string(),
+            quantity,
+        };
+        for (ore_per_a, a_produced, a_per_fuel) in &[(10, 10, 7), (9, 2, 3), (1, 7, 5)] {
+            let factory = Nanofactory::from_reactions(vec![
+                Reaction {
+                    reactants: vec![chemical(*ore_per_a, "ORE")],
+                    product: chemical(*a_produced, "A"),
+                },
+                Reaction {
+                    reactants: vec![chemical(*a_per_fuel, "A")],
+                    product: chemical(1, "FUEL"),
+                },
+            ]);
+            assert_ore_is_monotonic(&factory, 1..50);
+        }
+    }
+    #[test]
+    fn test_input_is_monotonic_around_part_2() {
+        let factory = Nanofactory::from_str(include_str!("input.txt")).unwrap();
+        assert_ore_is_monotonic(&factory, 1..200);
+        assert_ore_is_monotonic(&factory, 3445149..3445349);
+    }
+    #[test]
     fn test_small_example() {
         test_num_ore(
             "10 ORE => 10 A
@@ -1,14 +1,16 @@
 #![deny(warnings)]
 
 use direction::{CardinalDirection, CardinalDirectionIter, Coord};
-use intcode_computer::{ComputationStatus, Computer};
+use intcode_computer::{Computer, Yield};
 use maze;
-use petgraph::Direction;
 use std::collections::{HashMap, VecDeque};
 use std::{
+    convert::TryFrom,
     fmt::{self, Display, Formatter},
     str::FromStr,
+    time::Instant,
 };
+use structopt::StructOpt;
 
 fn direction_code(direction: CardinalDirection) -> isize {
     match direction {
@@ -65,13 +67,13 @@ enum ExplorationStep {
     FoundOxygen,
 }
 
-impl FromStr for ExplorationStep {
-    type Err = String;
-    fn from_str(x: &str) -> Result<Self, Self::Err> {
+impl TryFrom<isize> for ExplorationStep {
+    type Error = String;
+    fn try_from(x: isize) -> Result<Self, Self::Error> {
         match x {
-            "0" => Ok(Self::HitWall),
-            "1" => Ok(Self::MovedOneStep),
-            "2" => Ok(Self::FoundOxygen),
+            0 => Ok(Self::HitWall),
+            1 => Ok(Self::MovedOneStep),
+            2 => Ok(Self::FoundOxygen),
             _ => Err(format!("Can't construct ExplorationStep from {}", x)),
         }
     }
@@ -90,21 +92,67 @@ impl Maze {
     fn new(map: HashMap<Coord, TileContent>) -> Self {
         Self(maze::Maze::new(map))
     }
-    fn shortest_path_to_oxygen(&self) -> usize {
+    /// The distance to the oxygen tank, and the nodes of the maze on the way.
+    /// None if the oxygen tank is walled off from the starting point
+    fn shortest_path_to_oxygen(&self) -> Option<(usize, Vec<Coord>)> {
+        let start = self.0.find_tile(TileContent::StartingPoint).unwrap();
+        let destination = self.0.find_tile(TileContent::OxygenTank)?;
+        if self
+            .0
+            .unreachable_interesting_tiles(start)
+            .contains(&destination)
+        {
+            return None;
+        }
+        let (graph, indices) = self.0.as_graph_from_indexed(start);
+        maze::Maze::<TileContent>::shortest_path_with_route_indexed(
+            &graph,
+            &indices,
+            start,
+            destination,
+        )
+    }
+    /// Time going from the start to the oxygen tank through the graph of the whole maze versus
+    /// running A* directly on the tiles, and see how much the A* heuristic helps
+    fn compare_shortest_path_strategies(&self) {
         let start = self.0.find_tile(TileContent::StartingPoint).unwrap();
         let destination = self.0.find_tile(TileContent::OxygenTank).unwrap();
-        let graph = self.0.as_graph_from(start);
-        maze::Maze::<TileContent>::shortest_path(&graph, start, destination).unwrap()
+        let now = Instant::now();
+        let (graph, indices) = self.0.as_graph_from_indexed(start);
+        let through_graph =
+            maze::Maze::<TileContent>::shortest_path_indexed(&graph, &indices, start, destination);
+        let graph_time = now.elapsed();
+        let now = Instant::now();
+        let direct = self.0.shortest_path_direct(start, destination);
+        let direct_time = now.elapsed();
+        println!(
+            "shortest path through the graph: {:?} in {:?}, directly on the tiles: {:?} in {:?}",
+            through_graph, graph_time, direct, direct_time
+        );
+        let expansions = |heuristic| {
+            maze::Maze::<TileContent>::shortest_path_stats_indexed(
+                &graph,
+                &indices,
+                start,
+                destination,
+                heuristic,
+            )
+            .map(|(_cost, expansions)| expansions)
+            .unwrap()
+        };
+        println!(
+            "nodes expanded by A* with the manhattan heuristic: {}, by Dijkstra: {}",
+            expansions(maze::Heuristic::Manhattan),
+            expansions(maze::Heuristic::Zero)
+        );
     }
     fn total_time_for_oxyen_to_fill_maze(&self) -> usize {
         let start = self.0.find_tile(TileContent::OxygenTank).unwrap();
-        let graph = self.0.as_graph_from(start);
-        graph
-            .externals(Direction::Outgoing)
-            .map(|dead_end| {
-                let destination = graph.node_weight(dead_end).unwrap().clone();
-                maze::Maze::<TileContent>::shortest_path(&graph, start, destination).unwrap()
-            })
+        let (graph, indices) = self.0.as_graph_from_indexed(start);
+        // The oxygen's eccentricity: the farthest tile from it is the last to fill up
+        maze::Maze::<TileContent>::distances_from_indexed(&graph, &indices, start)
+            .values()
+            .copied()
             .max()
             .unwrap()
     }
@@ -132,29 +180,57 @@ impl Robot {
             backtracking: false,
         }
     }
-    fn walk_maze(&mut self, primary_direction: CardinalDirection) {
+    /// Tell the robot to move in a direction and hear back what happened. None once its program
+    /// halted
+    fn try_moving(&mut self, direction: CardinalDirection) -> Option<ExplorationStep> {
+        let mut input = None;
+        loop {
+            match self.computer.resume(input.take()).unwrap() {
+                Yield::NeedInput => input = Some(direction_code(direction)),
+                Yield::Output(output) => return Some(ExplorationStep::try_from(output).unwrap()),
+                Yield::Halt => return None,
+            }
+        }
+    }
+    /// Explore, favouring the primary direction, until the oxygen tank turns up and return where
+    /// it is. None if the robot's program halted first
+    fn walk_maze(&mut self, primary_direction: CardinalDirection) -> Option<Coord> {
         let mut direction = primary_direction;
-        let mut status = ComputationStatus::StarvingForMockInput;
-        while !self
-            .maze
-            .values()
-            .any(|tile| tile == &TileContent::OxygenTank)
-            && status != ComputationStatus::Done
-        {
-            self.computer
-                .set_mock_io_input(&format!("{}", direction_code(direction)));
-            status = self.computer.compute().unwrap();
-            let output = self.computer.get_mock_io_output().unwrap();
-            let step = ExplorationStep::from_str(output.trim()).unwrap();
+        loop {
+            let step = self.try_moving(direction)?;
             direction = self.explore(step, direction, primary_direction);
+            if step == ExplorationStep::FoundOxygen {
+                return Some(self.robot);
+            }
         }
     }
-    fn explore(
-        &mut self,
-        step: ExplorationStep,
-        direction: CardinalDirection,
-        primary_direction: CardinalDirection,
-    ) -> CardinalDirection {
+    /// Stop mapping the maze as soon as the oxygen tank is found
+    fn walk_until_found(&mut self) -> Option<Coord> {
+        self.walk_maze(CardinalDirection::North)
+    }
+    /// Map the whole maze in a single depth first walk: step into unknown tiles while there are
+    /// any around, otherwise retrace our steps. Done once we're back at the start with nothing
+    /// left to explore, or if the robot's program halted
+    fn explore_all(&mut self) {
+        loop {
+            let unknown = CardinalDirectionIter::new()
+                .find(|direction| self.tile_ahead(*direction).is_none());
+            self.backtracking = unknown.is_none();
+            let direction = match unknown {
+                Some(direction) => direction,
+                None => match self.direction_stack.pop_back() {
+                    Some(direction) => direction.opposite(),
+                    None => return,
+                },
+            };
+            match self.try_moving(direction) {
+                Some(step) => self.record(step, direction),
+                None => return,
+            }
+        }
+    }
+    /// Update the map and the robot's position with what happened when moving in that direction
+    fn record(&mut self, step: ExplorationStep, direction: CardinalDirection) {
         match step {
             ExplorationStep::HitWall => self.insert_tile_ahead(direction, TileContent::Wall),
             ExplorationStep::MovedOneStep => self.move_one_step(direction),
@@ -163,6 +239,14 @@ impl Robot {
                 self.move_one_step(direction);
             }
         }
+    }
+    fn explore(
+        &mut self,
+        step: ExplorationStep,
+        direction: CardinalDirection,
+        primary_direction: CardinalDirection,
+    ) -> CardinalDirection {
+        self.record(step, direction);
         self.decide_next_direction(primary_direction)
     }
     fn decide_next_direction(&mut self, primary_direction: CardinalDirection) -> CardinalDirection {
@@ -201,20 +285,153 @@ impl Robot {
     }
 }
 
+#[derive(Debug, StructOpt)]
+#[structopt(name = "oxygen", about = "Oxygen System.")]
+struct Opt {
+    /// Time the ways of finding the shortest path to the oxygen tank
+    #[structopt(long)]
+    bench: bool,
+}
+
 fn main() {
-    let mut full_maze = HashMap::default();
-    let robot = Robot::new(include_str!("input.txt"));
-    for primary_direction in CardinalDirectionIter::new() {
-        let mut robot = robot.clone();
-        robot.walk_maze(primary_direction);
-        full_maze.extend(robot.maze);
-    }
-    let full_maze = Maze::new(full_maze);
-    println!("{}", full_maze);
-    let part_1 = full_maze.shortest_path_to_oxygen();
+    let mut robot = Robot::new(include_str!("input.txt"));
+    let mut scout = robot.clone();
+    scout
+        .walk_until_found()
+        .expect("The robot stopped before finding the oxygen tank");
+    robot.explore_all();
+    let full_maze = Maze::new(robot.maze);
+    // Every open tile was probed in all directions
+    assert!(full_maze.0.is_enclosed());
+    let (part_1, route) = full_maze
+        .shortest_path_to_oxygen()
+        .expect("The oxygen tank can't be reached from the starting point");
+    println!("{}", full_maze.0.display_with_path(&route));
     assert_eq!(248, part_1);
+    // The maze has no loops, so the scout's partial map already holds the shortest path
+    let scouted = Maze::new(scout.maze);
+    assert_eq!(
+        Some(part_1),
+        scouted.shortest_path_to_oxygen().map(|(cost, _)| cost)
+    );
     println!("part 1: {}", part_1);
+    if Opt::from_args().bench {
+        full_maze.compare_shortest_path_strategies();
+    }
     let part_2 = full_maze.total_time_for_oxyen_to_fill_maze();
     assert_eq!(382, part_2);
     println!("part 2: {}", part_2);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    fn glyph_of(tile: &TileContent) -> char {
+        match tile {
+            TileContent::Empty => '.',
+            TileContent::Wall => '#',
+            TileContent::OxygenTank => 'O',
+            TileContent::Robot => 'R',
+            TileContent::StartingPoint => 'S',
+            TileContent::Visited => 'v',
+        }
+    }
+    #[test]
+    fn test_explore_all_finds_what_four_walks_found() {
+        let robot = Robot::new(include_str!("input.txt"));
+        // The union of four walks favouring each direction, stopping at the oxygen tank
+        let mut four_walks = maze::Maze::new(HashMap::new());
+        for primary_direction in CardinalDirectionIter::new() {
+            let mut robot = robot.clone();
+            assert!(robot.walk_maze(primary_direction).is_some());
+            four_walks.merge(&maze::Maze::new(robot.maze));
+        }
+        let mut single_walk = robot;
+        single_walk.explore_all();
+        for (coord, tile) in &four_walks.0 {
+            assert_eq!(
+                Some(*tile == TileContent::Wall),
+                single_walk
+                    .maze
+                    .get(coord)
+                    .map(|tile| *tile == TileContent::Wall),
+                "{:?}",
+                coord
+            );
+        }
+        assert!(single_walk.maze.len() > four_walks.0.len());
+    }
+    #[test]
+    fn test_shortest_path_strategies_agree() {
+        let mut robot = Robot::new(include_str!("input.txt"));
+        robot.explore_all();
+        let maze = Maze::new(robot.maze).0;
+        let start = maze.find_tile(TileContent::StartingPoint).unwrap();
+        let destination = maze.find_tile(TileContent::OxygenTank).unwrap();
+        let graph = maze.as_graph_from(start);
+        assert_eq!(
+            maze::Maze::<TileContent>::shortest_path(&graph, start, destination),
+            maze.shortest_path_direct(start, destination)
+        );
+        let stats = |heuristic| {
+            maze::Maze::<TileContent>::shortest_path_stats(&graph, start, destination, heuristic)
+                .unwrap()
+        };
+        let (a_star_cost, a_star_expansions) = stats(maze::Heuristic::Manhattan);
+        let (dijkstra_cost, dijkstra_expansions) = stats(maze::Heuristic::Zero);
+        assert_eq!(248, a_star_cost);
+        assert_eq!(a_star_cost, dijkstra_cost);
+        assert!(a_star_expansions <= dijkstra_expansions);
+    }
+    #[test]
+    fn test_graph_weights_cover_the_corridors() {
+        let mut robot = Robot::new(include_str!("input.txt"));
+        robot.explore_all();
+        let maze = Maze::new(robot.maze).0;
+        let start = maze.find_tile(TileContent::StartingPoint).unwrap();
+        let graph = maze.as_graph_from(start);
+        let annotated = maze.annotate_graph(&graph, glyph_of);
+        assert_eq!(
+            graph.node_count(),
+            annotated.lines().filter(|line| line.contains("):")).count()
+        );
+        // The maze has no loops, so every open tile but the start is walked by exactly one edge
+        let open_tiles = maze
+            .0
+            .values()
+            .filter(|tile| **tile != TileContent::Wall)
+            .count();
+        let total_weight = graph
+            .raw_edges()
+            .iter()
+            .map(|edge| edge.weight)
+            .sum::<usize>();
+        assert_eq!(open_tiles - 1, total_weight);
+    }
+    #[test]
+    fn test_ascii_round_trip() {
+        let tile_of = |c| match c {
+            '#' => TileContent::Wall,
+            'O' => TileContent::OxygenTank,
+            'R' => TileContent::Robot,
+            'S' => TileContent::StartingPoint,
+            'v' => TileContent::Visited,
+            _ => TileContent::Empty,
+        };
+        let map = [
+            ((-1, -1), TileContent::Wall),
+            ((0, -1), TileContent::Visited),
+            ((1, -1), TileContent::OxygenTank),
+            ((-1, 0), TileContent::Robot),
+            ((0, 0), TileContent::StartingPoint),
+            ((2, 1), TileContent::Empty),
+        ]
+        .iter()
+        .map(|((x, y), tile)| (Coord::new(*x, *y), *tile))
+        .collect::<HashMap<_, _>>();
+        let maze = maze::Maze::new(map.clone());
+        let ascii = maze.to_ascii(glyph_of);
+        assert_eq!("-1,-1\n#vO \nRS  \n   .\n", ascii);
+        assert_eq!(map, maze::Maze::from_ascii(&ascii, tile_of).unwrap().0);
+    }
+}
@@ -1,19 +1,27 @@
 #![deny(warnings)]
 
 use std::iter::repeat;
+use timing::timed;
 
 const BASE_PATTERN: [isize; 4] = [0, 1, 0, -1];
 
-fn last_digit(n: isize) -> char {
-    format!("{}", n).chars().last().unwrap()
+fn last_digit(n: isize) -> u8 {
+    (n.abs() % 10) as u8
 }
 
-fn digits(n: &'_ str) -> impl Iterator<Item = isize> + '_ {
-    n.chars().map(|c| c.to_digit(10).unwrap() as isize)
+fn digits(n: &str) -> Vec<u8> {
+    n.chars().map(|c| c.to_digit(10).unwrap() as u8).collect()
 }
 
-fn nth_pattern(n: usize) -> impl Iterator<Item = isize> + 'static {
-    BASE_PATTERN
+fn from_digits(digits: &[u8]) -> String {
+    digits
+        .iter()
+        .map(|d| std::char::from_digit(u32::from(*d), 10).unwrap())
+        .collect()
+}
+
+fn nth_pattern(base_pattern: &[isize], n: usize) -> impl Iterator<Item = isize> + '_ {
+    base_pattern
         .iter()
         .flat_map(move |i| repeat(i).take(n + 1))
         .cycle()
@@ -21,13 +29,14 @@ fn nth_pattern(n: usize) -> impl Iterator<Item = isize> + 'static {
         .cloned()
 }
 
-fn phase(input: &str) -> String {
-    (0..input.chars().count())
+fn phase_digits(input: &[u8], base_pattern: &[isize]) -> Vec<u8> {
+    (0..input.len())
         .map(|index| {
             last_digit(
-                digits(&input)
-                    .zip(nth_pattern(index))
-                    .map(|(d, p)| d * p)
+                input
+                    .iter()
+                    .zip(nth_pattern(base_pattern, index))
+                    .map(|(d, p)| *d as isize * p)
                     .sum(),
             )
         })
@@ -35,11 +44,11 @@ fn phase(input: &str) -> String {
 }
 
 fn flawed_frequency_transmission(input: &str, n: usize) -> String {
-    let mut result = input.to_string();
+    let mut result = digits(input);
     for _ in 0..n {
-        result = phase(&result);
+        result = phase_digits(&result, &BASE_PATTERN);
     }
-    result
+    from_digits(&result)
 }
 
 fn nth_eight_digits(n: usize, s: &str) -> String {
@@ -50,10 +59,11 @@ fn first_eight_digits(s: &str) -> String {
     nth_eight_digits(0, s)
 }
 
-fn real_fft(input: &'static str, n: usize) -> String {
+/// The message in the real signal: the input repeated `repeat_factor` times, after `n` phases
+fn real_fft(input: &'static str, n: usize, repeat_factor: usize) -> String {
     let message_offset: usize = input.chars().take(7).collect::<String>().parse().unwrap();
     let original_length = input.chars().count();
-    let real_length = 10_000 * original_length;
+    let real_length = repeat_factor * original_length;
     // If the total length is less than half the message offset, we are in a special case that is
     // easy to optimize:
     // Here what the pattern looks like:
@@ -101,15 +111,17 @@ fn real_fft(input: &'static str, n: usize) -> String {
 }
 
 fn main() {
-    let part_1 = first_eight_digits(&flawed_frequency_transmission(
-        include_str!("input.txt").trim(),
-        100,
-    ));
+    let part_1 = timed("part 1", || {
+        first_eight_digits(&flawed_frequency_transmission(
+            include_str!("input.txt").trim(),
+            100,
+        ))
+    });
     assert_eq!("18933364".to_string(), part_1);
-    println!("part 1: {}", part_1);
-    let part_2 = real_fft(include_str!("input.txt").trim(), 100);
+    let part_2 = timed("part 2", || {
+        real_fft(include_str!("input.txt").trim(), 100, 10_000)
+    });
     assert_eq!("28872305".to_string(), part_2);
-    println!("part 2: {}", part_2);
 }
 
 #[cfg(test)]
@@ -128,6 +140,33 @@ mod tests {
         );
     }
     #[test]
+    fn test_phase_digits() {
+        assert_eq!(7, last_digit(-17));
+        assert_eq!(0, last_digit(40));
+        assert_eq!(
+            vec![4, 8, 2, 2, 6, 1, 5, 8],
+            phase_digits(&[1, 2, 3, 4, 5, 6, 7, 8], &BASE_PATTERN)
+        );
+        assert_eq!("12345678", from_digits(&digits("12345678")));
+    }
+    #[test]
+    fn test_other_base_pattern() {
+        // Every position sums all the digits
+        assert_eq!(vec![6, 6, 6], phase_digits(&[1, 2, 3], &[1]));
+        assert_eq!(vec![0, 0, 0], phase_digits(&[1, 2, 3], &[0]));
+    }
+    #[test]
+    fn test_real_fft_repeat_factor() {
+        // The message offset of 6 is in the second half of the signal without repeating it
+        let input_signal = "0000006123";
+        for phases in 1..5 {
+            assert_eq!(
+                nth_eight_digits(6, &flawed_frequency_transmission(input_signal, phases)),
+                real_fft(input_signal, phases, 1)
+            );
+        }
+    }
+    #[test]
     fn test_large_example() {
         let input_signal = "69317163492948606335995924319873";
         assert_eq!(
@@ -138,14 +177,14 @@ mod tests {
     #[test]
     fn test_real_fft() {
         let input_signal = "03036732577212944063491565474664";
-        assert_eq!("84462026", real_fft(input_signal, 100));
+        assert_eq!("84462026", real_fft(input_signal, 100, 10_000));
 
         // These two examples don't exhibit the property I rely on for optimizing this case, so
         // f*ck em :)
         // let input_signal = "02935109699940807407585447034323 ";
-        // assert_eq!("78725270", real_fft(input_signal, 3));
+        // assert_eq!("78725270", real_fft(input_signal, 3, 10_000));
 
         // let input_signal = "03081770884921959731165446850517 ";
-        // assert_eq!("53553731", real_fft(input_signal, 3));
+        // assert_eq!("53553731", real_fft(input_signal, 3, 10_000));
     }
 }
@@ -3,8 +3,9 @@
 use direction::{CardinalDirection, CardinalDirectionIter, Coord};
 use intcode_computer::{ComputationStatus, Computer};
 use itertools::Itertools;
-use map_display::MapDisplay;
-use std::collections::HashMap;
+use map_display::{MapDisplay, RowMajor, Turn, TurnToward};
+use maze::{Maze, MazeTile};
+use std::collections::{HashMap, HashSet};
 use std::fmt::{self, Debug, Display, Formatter};
 use std::str::FromStr;
 
@@ -13,6 +14,8 @@ enum TileContent {
     Empty,
     Scaffold,
     Robot,
+    /// Scaffold crossing itself. The camera can't tell, but the maze built from its picture can
+    Intersection,
     Ascii(char),
 }
 
@@ -27,17 +30,29 @@ impl From<char> for TileContent {
         match c {
             '.' => TileContent::Empty,
             '#' => TileContent::Scaffold,
-            '^' | '<' | '>' => TileContent::Robot,
+            '^' | 'v' | '<' | '>' => TileContent::Robot,
             _ => TileContent::Ascii(c),
         }
     }
 }
 
+impl TileContent {
+    /// Unlike `from`, only accepts what the camera is expected to see. (`TryFrom<char>` comes for
+    /// free with `From<char>`, and never fails)
+    fn try_from_camera(c: char) -> Result<Self, String> {
+        match c {
+            '.' | '#' | '^' | 'v' | '<' | '>' => Ok(Self::from(c)),
+            _ if c.is_ascii_digit() => Ok(Self::Ascii(c)),
+            _ => Err(format!("Unexpected character from the camera: {:?}", c)),
+        }
+    }
+}
+
 impl Display for TileContent {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         let px = match self {
             Self::Empty => "  ".to_string(),
-            Self::Scaffold => "🚧".to_string(),
+            Self::Scaffold | Self::Intersection => "🚧".to_string(),
             Self::Robot => "🤖".to_string(),
             Self::Ascii(c) => format!("{}", c),
         };
@@ -45,26 +60,46 @@ impl Display for TileContent {
     }
 }
 
+impl MazeTile for TileContent {
+    fn is_wall(self) -> bool {
+        match self {
+            Self::Scaffold | Self::Robot | Self::Intersection => false,
+            Self::Empty | Self::Ascii(_) => true,
+        }
+    }
+    fn is_interesting(self) -> bool {
+        self == Self::Intersection
+    }
+}
+
 struct Camera {
     map: HashMap<Coord, TileContent>,
+    /// Where the robot stands. The robot hides the scaffold under it, so the map has scaffold there
+    start: Option<Coord>,
 }
 
 impl Camera {
-    fn new(computer_output: &str) -> Self {
-        let string_view = computer_output
-            .trim()
-            .split('\n')
-            .map(|s| {
-                if let Ok(i) = s.parse::<u8>() {
-                    format!("{}", char::from(i))
-                } else {
-                    format!("{}", s)
-                }
-            })
-            .collect::<String>();
-        Self {
-            map: MapDisplay::from_str(&string_view).unwrap().0,
+    /// Read the picture from the camera's ASCII screen
+    fn new(screen: &str) -> Result<Self, String> {
+        let mut map = HashMap::new();
+        // A blank line ends the picture, before any prompt which may follow it
+        let picture = screen.trim().split("\n\n").next().unwrap_or_default();
+        for (y, line) in picture.split('\n').enumerate() {
+            for (x, c) in line.chars().enumerate() {
+                map.insert(
+                    Coord::new(x as i32, y as i32),
+                    TileContent::try_from_camera(c)?,
+                );
+            }
         }
+        let start = map
+            .iter()
+            .find(|(_coord, tile)| **tile == TileContent::Robot)
+            .map(|(coord, _tile)| *coord);
+        if let Some(start) = start {
+            map.insert(start, TileContent::Scaffold);
+        }
+        Ok(Self { map, start })
     }
     fn is_intersection(&self, coord: Coord) -> bool {
         self.map.get(&coord) == Some(&TileContent::Scaffold)
@@ -72,37 +107,52 @@ impl Camera {
                 .map(|direction| coord + direction.coord())
                 .all(|neighbor| self.map.get(&neighbor) == Some(&TileContent::Scaffold))
     }
-    fn alignment_parameter(coord: Coord) -> i32 {
-        coord.x * coord.y
+    /// The scaffold as a maze, with its intersections as interesting tiles
+    fn as_maze(&self) -> Maze<TileContent> {
+        Maze::new(
+            self.map
+                .iter()
+                .map(|(coord, tile)| {
+                    if self.is_intersection(*coord) {
+                        (*coord, TileContent::Intersection)
+                    } else {
+                        (*coord, *tile)
+                    }
+                })
+                .collect(),
+        )
     }
-    fn total_alignment_parameter(&self) -> i32 {
-        self.map
+    /// The camera's coordinates start at the top left corner, so they are never negative. Their
+    /// product may still not fit in an i32 for a large enough picture
+    fn alignment_parameter(coord: Coord) -> i64 {
+        i64::from(coord.x) * i64::from(coord.y)
+    }
+    /// Where the scaffold crosses itself, in reading order
+    fn intersections(&self) -> Vec<Coord> {
+        let mut intersections = self
+            .map
             .keys()
             .filter(|coord| self.is_intersection(**coord))
-            .map(|coord| Self::alignment_parameter(*coord))
+            .cloned()
+            .collect::<Vec<_>>();
+        intersections.sort_by_key(|coord| RowMajor(*coord));
+        intersections
+    }
+    fn total_alignment_parameter(&self) -> i64 {
+        self.intersections()
+            .into_iter()
+            .map(Self::alignment_parameter)
             .sum()
     }
 }
 
 impl Display for Camera {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", MapDisplay(self.map.clone()))
-    }
-}
-
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
-enum Turn {
-    Left,
-    Right,
-}
-
-impl Display for Turn {
-    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        let px = match self {
-            Self::Left => 'L',
-            Self::Right => 'R',
-        };
-        write!(f, "{}", px)
+        let mut map = self.map.clone();
+        if let Some(start) = self.start {
+            map.insert(start, TileContent::Robot);
+        }
+        write!(f, "{}", MapDisplay(map))
     }
 }
 
@@ -182,44 +232,41 @@ struct Robot {
     position: Coord,
     facing: CardinalDirection,
     map: HashMap<Coord, TileContent>,
+    visited: Vec<Coord>,
 }
 
 impl Robot {
-    fn new(map: HashMap<Coord, TileContent>) -> Self {
-        let position = map
-            .iter()
-            .find(|(_k, v)| v == &&TileContent::Robot)
-            .map(|(k, _v)| *k)
-            .unwrap();
+    fn new(camera: &Camera) -> Result<Self, String> {
+        let position = camera
+            .start
+            .ok_or_else(|| "The camera didn't see the robot".to_string())?;
+        let map = camera.map.clone();
         let facing = CardinalDirection::North;
-        Self {
+        Ok(Self {
             position,
             facing,
             map,
-        }
+            visited: vec![position],
+        })
     }
     fn forward(&self) -> Coord {
         self.position + self.facing.coord()
     }
-    fn left(&self) -> Coord {
-        self.position + self.facing.left90().coord()
-    }
-    fn right(&self) -> Coord {
-        self.position + self.facing.right90().coord()
-    }
     fn can_go_forward(&self) -> bool {
-        self.map.get(&self.forward()) == Some(&TileContent::Scaffold)
+        self.can_face(self.facing)
     }
-    fn can_turn(&self, turn: Turn) -> bool {
-        self.map.get(&{
-            match turn {
-                Turn::Left => self.left(),
-                Turn::Right => self.right(),
-            }
-        }) == Some(&TileContent::Scaffold)
+    /// Whether there is scaffold next to the robot in that direction
+    fn can_face(&self, direction: CardinalDirection) -> bool {
+        self.map.get(&(self.position + direction.coord())) == Some(&TileContent::Scaffold)
+    }
+    /// Where the scaffold continues once the robot can't go forward any more
+    fn side_to_turn_to(&self) -> Option<CardinalDirection> {
+        vec![self.facing.left90(), self.facing.right90()]
+            .into_iter()
+            .find(|direction| self.can_face(*direction))
     }
     fn is_dead_end(&self) -> bool {
-        !(self.can_go_forward() || self.can_turn(Turn::Left) || self.can_turn(Turn::Right))
+        !self.can_go_forward() && self.side_to_turn_to().is_none()
     }
     fn find_shortest_total_sequence(&mut self) -> Vec<Move> {
         let mut moves = vec![];
@@ -228,20 +275,35 @@ impl Robot {
             while self.can_go_forward() {
                 num_steps += 1;
                 self.position = self.forward();
+                self.visited.push(self.position);
             }
             if num_steps > 0 {
                 moves.push(Move::Forward(num_steps));
             }
-            if self.can_turn(Turn::Left) {
-                moves.push(Move::Rotate(Turn::Left));
-                self.facing = self.facing.left90();
-            } else if self.can_turn(Turn::Right) {
-                moves.push(Move::Rotate(Turn::Right));
-                self.facing = self.facing.right90();
+            if let Some(direction) = self.side_to_turn_to() {
+                moves.extend(
+                    self.facing
+                        .turn_toward(direction)
+                        .into_iter()
+                        .map(Move::Rotate),
+                );
+                self.facing = direction;
             }
         }
         moves
     }
+    /// Every tile walked on so far, in order. Intersections show up twice
+    fn visited_path(&self) -> Vec<Coord> {
+        self.visited.clone()
+    }
+    /// Whether the walk so far went over every scaffold tile
+    fn covers_scaffold(&self) -> bool {
+        let visited = self.visited_path().into_iter().collect::<HashSet<_>>();
+        self.map
+            .iter()
+            .filter(|(_coord, tile)| **tile == TileContent::Scaffold)
+            .all(|(coord, _tile)| visited.contains(coord))
+    }
     fn break_sequence_up(sequence: &[Move]) -> MovementRoutine {
         // Just broke the sequence by eye. Sometimes, it's easier to spot patterns by eye than with
         // fancy algos...
@@ -270,33 +332,155 @@ impl Robot {
 fn main() {
     {
         let mut computer = Computer::from_str(include_str!("input.txt")).unwrap();
-        computer.set_mock_io_input("");
+        computer.set_mock_io_input("").unwrap();
         computer.compute().unwrap();
-        let output = computer.get_mock_io_output().unwrap();
-        let camera = Camera::new(&output);
+        let camera = Camera::new(&computer.ascii_screen()).unwrap();
         println!("{}", camera);
 
         let part_1 = camera.total_alignment_parameter();
         assert_eq!(6024, part_1);
-        println!("part 1: {}", part_1);
+        let intersections = camera.intersections();
+        assert_eq!(12, intersections.len());
+        assert_eq!(
+            camera
+                .as_maze()
+                .find_tiles_sorted(&TileContent::is_interesting),
+            intersections
+        );
+        println!("part 1: {} ({} intersections)", part_1, intersections.len());
     }
     {
         let mut computer = Computer::from_str(include_str!("input.txt")).unwrap();
         // Wake up, beebop!
         computer.data[0] = 2;
-        computer.set_mock_io_input("");
+        computer.set_mock_io_input("").unwrap();
         computer.compute().unwrap();
-        let output = computer.get_mock_io_output().unwrap();
-        let camera = Camera::new(&output.trim());
-        let mut bot = Robot::new(camera.map.clone());
+        let camera = Camera::new(&computer.ascii_screen()).unwrap();
+        let mut bot = Robot::new(&camera).unwrap();
         let input = bot.create_computer_input_sequence();
-        computer.set_mock_io_input(&input);
+        assert!(bot.covers_scaffold());
+        computer.set_mock_io_input(&input).unwrap();
         let status = computer.compute().unwrap();
         assert_eq!(ComputationStatus::Done, status);
-        let output = computer.get_mock_io_output().unwrap();
-        let screen = format!("{}", Camera::new(&output.trim()));
-        let part_2 = screen.trim().split("\n").last().unwrap();
-        assert_eq!("897344", part_2);
+        // The robot reports the dust it collected after the last line of its video feed
+        let part_2: isize = computer.ascii_lines().last().unwrap().parse().unwrap();
+        assert_eq!(897344, part_2);
         println!("part 2: {}", part_2);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_unexpected_camera_output() {
+        assert_eq!(Ok(TileContent::Robot), TileContent::try_from_camera('v'));
+        assert_eq!(
+            Ok(TileContent::Ascii('7')),
+            TileContent::try_from_camera('7')
+        );
+        assert!(TileContent::try_from_camera('?').is_err());
+        assert_eq!(TileContent::Ascii('?'), TileContent::from('?'));
+        assert!(Camera::new("#.\n#?\n").is_err());
+    }
+    #[test]
+    fn test_as_maze() {
+        let camera = Camera::new(
+            "\
+..#..........
+..#..........
+#######...###
+#.#...#...#.#
+#############
+..#...#...#..
+..#####...^..
+",
+        )
+        .unwrap();
+        assert_eq!(76, camera.total_alignment_parameter());
+        let maze = camera.as_maze();
+        let intersections = maze.find_tiles_sorted(&TileContent::is_interesting);
+        assert_eq!(camera.intersections(), intersections);
+        assert_eq!(
+            vec![
+                Coord::new(2, 2),
+                Coord::new(2, 4),
+                Coord::new(6, 4),
+                Coord::new(10, 4)
+            ],
+            intersections
+        );
+    }
+    #[test]
+    fn test_intersections() {
+        let camera = Camera::new(
+            "\
+..#...#..
+#########
+..#...#..
+.######..
+..#......
+",
+        )
+        .unwrap();
+        assert_eq!(
+            vec![Coord::new(2, 1), Coord::new(6, 1), Coord::new(2, 3)],
+            camera.intersections()
+        );
+        assert_eq!(2 + 6 + 6, camera.total_alignment_parameter());
+    }
+    #[test]
+    fn test_large_alignment_parameter() {
+        let center = Coord::new(50000, 50000);
+        let map = std::iter::once(center)
+            .chain(CardinalDirectionIter::new().map(|direction| center + direction.coord()))
+            .map(|coord| (coord, TileContent::Scaffold))
+            .collect();
+        let camera = Camera { map, start: None };
+        assert_eq!(2_500_000_000, camera.total_alignment_parameter());
+    }
+    #[test]
+    fn test_visited_path() {
+        let camera = Camera::new(
+            "\
+#######
+#.....#
+^.....#
+",
+        )
+        .unwrap();
+        let mut bot = Robot::new(&camera).unwrap();
+        bot.find_shortest_total_sequence();
+        let visited = bot.visited_path();
+        assert_eq!(11, visited.len());
+        assert_eq!(Some(&Coord::new(0, 2)), visited.first());
+        assert_eq!(Some(&Coord::new(6, 2)), visited.last());
+        assert!(bot.covers_scaffold());
+    }
+    #[test]
+    fn test_no_robot() {
+        let camera = Camera::new("..#..\n#####\n..#..").unwrap();
+        assert_eq!(
+            Some("The camera didn't see the robot".to_string()),
+            Robot::new(&camera).err()
+        );
+    }
+    #[test]
+    fn test_visited_path_misses_a_branch() {
+        let camera = Camera::new(
+            "\
+..#..
+#####
+..^..
+",
+        )
+        .unwrap();
+        let mut bot = Robot::new(&camera).unwrap();
+        bot.find_shortest_total_sequence();
+        assert_eq!(
+            vec![Coord::new(2, 2), Coord::new(2, 1), Coord::new(2, 0)],
+            bot.visited_path()
+        );
+        assert!(!bot.covers_scaffold());
+    }
+}
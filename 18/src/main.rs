@@ -1,4 +1,6 @@
-use maze::{Coord, Maze, MazeTile};
+use maze::{CardinalDirection, Coord, Maze, MazeTile};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
 use std::fmt::{self, Display, Formatter};
 use std::str::FromStr;
 
@@ -9,7 +11,6 @@ enum TileContent {
     Wall,
     Key(char),
     ClosedGate(char),
-    OpenGate(char),
 }
 
 impl TileContent {
@@ -65,60 +66,202 @@ impl Display for TileContent {
             Self::Wall => "🧱".to_string(),
             Self::Key(c) => format!("🗝\u{034f}{}", c), // U+034F U+0364
             Self::ClosedGate(c) => format!("🕳\u{034f}{}", c.to_lowercase()), // U+034F U+0364
-            Self::OpenGate(c) => format!(" \u{034f}{}", c.to_lowercase()), // U+034F U+0364
         };
         write!(f, "{}", px)
     }
 }
 
-fn all_paths(maze: Maze<TileContent>, point: Coord, mut path: Vec<usize>) -> Vec<usize> {
-    let graph = maze.as_graph_from(point);
-    let all_reachable_keys = maze.find_reachable_tiles(&graph, &TileContent::is_key);
+/// Where the key graph starts from
+const START: char = '@';
 
-    all_reachable_keys
+/// How the key graph refers to each starting point, in reading order: the first one is START and
+/// the others are numbered from 1, which leaves room for 10 of them
+fn start_labels(maze: &Maze<TileContent>) -> Result<HashMap<Coord, char>, String> {
+    let starts = maze.find_tiles_sorted(&|tile| tile == TileContent::StartingPoint);
+    if starts.len() > 10 {
+        return Err(format!(
+            "Too many starting points: {}, at most 10 are supported",
+            starts.len()
+        ));
+    }
+    Ok(starts
         .into_iter()
-        .map(|key_coord| {
-            let mut maze = maze.clone();
-            // Get the distance from last point to this key
-            let distance_to_key =
-                maze::Maze::<TileContent>::shortest_path(&graph, point, key_coord).unwrap();
-
-            // Pick up the key
-            let key = maze.0.insert(key_coord, TileContent::Empty);
-
-            // Open the gate that key opens
-            let key_id = match key {
-                Some(TileContent::Key(c)) => c,
-                _ => panic!("Expected a key at coordinate: {:?}!", key_coord),
+        .enumerate()
+        .map(|(index, coord)| {
+            let label = if index == 0 {
+                START
+            } else {
+                std::char::from_digit(index as u32, 10).unwrap()
             };
-            if let Some(gate_coord) = maze.find_tile(TileContent::ClosedGate(key_id)) {
-                let _ = maze.0.insert(gate_coord, TileContent::OpenGate(key_id));
+            (coord, label)
+        })
+        .collect())
+}
+
+/// Only valid for keys from a maze which went through `parse_maze`
+fn key_bit(key: char) -> u32 {
+    1 << (key as u32 - 'a' as u32)
+}
+
+/// Keys and gates are letters, so that the keys held fit in the bits of a u32
+fn parse_maze(input: &str) -> Result<Maze<TileContent>, String> {
+    let maze = Maze::<TileContent>::from_str(input)?;
+    for (coord, tile) in &maze.0 {
+        match tile {
+            TileContent::Key(key) | TileContent::ClosedGate(key) if !key.is_ascii_lowercase() => {
+                return Err(format!(
+                    "Invalid key or gate at ({}, {}): keys go from a to z, and gates from A to Z",
+                    coord.x, coord.y
+                ));
             }
+            _ => (),
+        }
+    }
+    Ok(maze)
+}
 
-            path.push(distance_to_key);
-            all_paths(maze.clone(), key_coord, path.clone())
-                .into_iter()
-                .min()
-                .unwrap_or({
-                    println!("Path: {:?}", path);
-                    println!("Sum: {}", path.iter().sum::<usize>());
-                    path.iter().sum()
-                })
-        })
-        .collect()
+/// The keys which can be reached from a coordinate, with where they are, how far, and which gates
+/// must be open on the way, nearest first
+fn reachable_keys(maze: &Maze<TileContent>, from: Coord) -> Vec<(char, Coord, usize, Vec<char>)> {
+    let directions = [
+        CardinalDirection::North,
+        CardinalDirection::East,
+        CardinalDirection::South,
+        CardinalDirection::West,
+    ];
+    let mut keys = Vec::new();
+    let mut visited = HashSet::new();
+    visited.insert(from);
+    let mut queue = VecDeque::new();
+    queue.push_back((from, 0, Vec::new()));
+    while let Some((coord, distance, gates)) = queue.pop_front() {
+        for direction in &directions {
+            let neighbor = coord + direction.coord();
+            let tile = match maze.0.get(&neighbor) {
+                None | Some(TileContent::Wall) => continue,
+                Some(tile) => *tile,
+            };
+            if !visited.insert(neighbor) {
+                continue;
+            }
+            let mut gates = gates.clone();
+            match tile {
+                TileContent::ClosedGate(gate) => {
+                    gates.push(gate);
+                    gates.sort();
+                }
+                TileContent::Key(key) => keys.push((key, neighbor, distance + 1, gates.clone())),
+                _ => (),
+            }
+            queue.push_back((neighbor, distance + 1, gates));
+        }
+    }
+    keys
+}
+
+/// For each ordered pair of keys (or the start and a key), the distance of the corridor between
+/// them and the gates which must be open to walk it
+type KeyGraph = HashMap<(char, char), (usize, Vec<char>)>;
+
+fn key_graph(maze: &Maze<TileContent>) -> Result<KeyGraph, String> {
+    let starts = start_labels(maze)?;
+    let mut graph = HashMap::new();
+    for (origin, tile) in &maze.0 {
+        let from = match tile {
+            TileContent::StartingPoint => starts[origin],
+            TileContent::Key(key) => *key,
+            _ => continue,
+        };
+        for (key, _coord, distance, gates) in reachable_keys(maze, *origin) {
+            let _ = graph.insert((from, key), (distance, gates));
+        }
+    }
+    Ok(graph)
+}
+
+fn shortest_path(input: &str) -> Result<usize, String> {
+    collect_all_keys(&parse_maze(input)?)
+}
+
+/// Split the map in four around its single starting point, as in part 2, and send one robot into
+/// each quarter. Maps which already have four starting points are used as they are
+fn shortest_path_four_robots(input: &str) -> Result<usize, String> {
+    let mut maze = parse_maze(input)?;
+    let starts = maze.find_tiles(&|tile| tile == TileContent::StartingPoint);
+    if let [center] = starts[..] {
+        let _ = maze.0.insert(center, TileContent::Wall);
+        for direction in CardinalDirection::all() {
+            let _ = maze.0.insert(center + direction.coord(), TileContent::Wall);
+            let diagonal = center + direction.coord() + direction.right90().coord();
+            let _ = maze.0.insert(diagonal, TileContent::StartingPoint);
+        }
+    }
+    collect_all_keys(&maze)
 }
 
-fn shortest_path(input: &str) -> usize {
-    let maze = Maze::<TileContent>::from_str(input).unwrap();
-    println!("{}", maze);
-    let start = maze.find_tile(TileContent::StartingPoint).unwrap();
-    let all_paths = all_paths(maze, start, Vec::new());
-    println!("All paths: {:?}", all_paths);
-    all_paths.into_iter().min().unwrap()
+/// The fewest steps for the robots, one on each starting point, to collect every key. Only one
+/// robot moves at a time, and a key picked by any of them opens its gates for all
+fn collect_all_keys(maze: &Maze<TileContent>) -> Result<usize, String> {
+    let mut corridors = HashMap::<char, Vec<_>>::new();
+    for ((from, to), (distance, gates)) in key_graph(maze)? {
+        let required_keys = gates
+            .into_iter()
+            .map(key_bit)
+            .fold(0, |keys, key| keys | key);
+        corridors
+            .entry(from)
+            .or_default()
+            .push((to, distance, required_keys));
+    }
+    let all_keys = maze
+        .find_tiles(&TileContent::is_key)
+        .into_iter()
+        .filter_map(|coord| match maze.0[&coord] {
+            TileContent::Key(key) => Some(key_bit(key)),
+            _ => None,
+        })
+        .fold(0, |keys, key| keys | key);
+    let starts = start_labels(maze)?.values().copied().collect::<Vec<_>>();
+    // Dijkstra's algorithm over (last key picked by each robot, keys held so far)
+    let mut best = HashMap::new();
+    let mut queue = BinaryHeap::new();
+    queue.push(Reverse((0, starts, 0)));
+    while let Some(Reverse((distance, positions, keys))) = queue.pop() {
+        if keys == all_keys {
+            return Ok(distance);
+        }
+        if best
+            .get(&(positions.clone(), keys))
+            .is_some_and(|best| *best < distance)
+        {
+            continue;
+        }
+        for (robot, position) in positions.iter().enumerate() {
+            for (key, length, required_keys) in corridors.get(position).into_iter().flatten() {
+                if keys & key_bit(*key) != 0 || keys & required_keys != *required_keys {
+                    continue;
+                }
+                let mut next_positions = positions.clone();
+                next_positions[robot] = *key;
+                let state = (next_positions, keys | key_bit(*key));
+                let distance = distance + length;
+                if best.get(&state).is_none_or(|best| distance < *best) {
+                    let _ = best.insert(state.clone(), distance);
+                    queue.push(Reverse((distance, state.0, state.1)));
+                }
+            }
+        }
+    }
+    Err("Can't collect all the keys".to_string())
 }
 
 fn main() {
-    println!("part 1: {}", shortest_path(include_str!("input.txt")));
+    let part_1 = shortest_path(include_str!("input.txt")).unwrap();
+    assert_eq!(5406, part_1);
+    println!("part 1: {}", part_1);
+    let part_2 = shortest_path_four_robots(include_str!("input.txt")).unwrap();
+    assert_eq!(1938, part_2);
+    println!("part 2: {}", part_2);
 }
 
 #[cfg(test)]
@@ -130,7 +273,55 @@ mod tests {
 #b.A.@.a#
 #########";
         let shortest_path = shortest_path(input);
-        assert_eq!(8, shortest_path);
+        assert_eq!(Ok(8), shortest_path);
+    }
+    #[test]
+    fn test_invalid_keys() {
+        assert_eq!(
+            Err(
+                "Invalid key or gate at (3, 1): keys go from a to z, and gates from A to Z"
+                    .to_string()
+            ),
+            shortest_path("#####\n#@.?a#\n#####")
+        );
+        assert!(shortest_path("#####\n#@.é#\n#####").is_err());
+        assert_eq!(Ok(2), shortest_path("#####\n#@.a#\n#####"));
+    }
+    #[test]
+    fn test_too_many_starting_points() {
+        let input = "##############\n#@@@@@@@@@@@a#\n##############";
+        assert_eq!(
+            Err("Too many starting points: 11, at most 10 are supported".to_string()),
+            shortest_path(input)
+        );
+        let input = "############\n#@@@@@@@@@@a#\n############";
+        assert_eq!(Ok(1), shortest_path(input));
+    }
+    #[test]
+    fn test_key_graph() {
+        let input = "#########
+#b.A.@.a#
+#########";
+        let maze = Maze::<TileContent>::from_str(input).unwrap();
+        let graph = key_graph(&maze).unwrap();
+        assert_eq!(Some(&(2, vec![])), graph.get(&('@', 'a')));
+        assert_eq!(Some(&(4, vec!['a'])), graph.get(&('@', 'b')));
+        assert_eq!(Some(&(6, vec!['a'])), graph.get(&('a', 'b')));
+        assert_eq!(4, graph.len());
+    }
+    #[test]
+    fn test_reachable_keys() {
+        let input = "#########
+#b.A.@.a#
+#########";
+        let maze = Maze::<TileContent>::from_str(input).unwrap();
+        assert_eq!(
+            vec![
+                ('a', Coord::new(7, 1), 2, vec![]),
+                ('b', Coord::new(1, 1), 4, vec!['a'])
+            ],
+            reachable_keys(&maze, Coord::new(5, 1))
+        );
     }
     #[test]
     fn test_larger_example() {
@@ -140,7 +331,7 @@ mod tests {
 #d.....................#
 ########################";
         let shortest_path = shortest_path(input);
-        assert_eq!(86, shortest_path);
+        assert_eq!(Ok(86), shortest_path);
     }
     #[test]
     fn test_medium_constrained() {
@@ -150,7 +341,7 @@ mod tests {
 #.....@.a.B.c.d.A.e.F.g#
 ########################";
         let shortest_path = shortest_path(input);
-        assert_eq!(132, shortest_path);
+        assert_eq!(Ok(132), shortest_path);
     }
     #[test]
     fn test_medium_example() {
@@ -164,6 +355,51 @@ mod tests {
 #l.F..d...h..C.m#
 #################";
         let shortest_path = shortest_path(input);
-        assert_eq!(136, shortest_path);
+        assert_eq!(Ok(136), shortest_path);
+    }
+    #[test]
+    fn test_four_robots() {
+        let input = "#######
+#a.#Cd#
+##...##
+##.@.##
+##...##
+#cB#Ab#
+#######";
+        assert_eq!(Ok(8), shortest_path_four_robots(input));
+        let input = "#######
+#a.#Cd#
+##@#@##
+#######
+##@#@##
+#cB#Ab#
+#######";
+        assert_eq!(Ok(8), shortest_path_four_robots(input));
+        let input = "###############
+#d.ABC.#.....a#
+######@#@######
+###############
+######@#@######
+#b.....#.....c#
+###############";
+        assert_eq!(Ok(24), shortest_path_four_robots(input));
+        let input = "#############
+#DcBa.#.GhKl#
+#.###@#@#I###
+#e#d#####j#k#
+###C#@#@###J#
+#fEbA.#.FgHi#
+#############";
+        assert_eq!(Ok(32), shortest_path_four_robots(input));
+        let input = "#############
+#g#f.D#..h#l#
+#F###e#E###.#
+#dCba@#@BcIJ#
+#############
+#nK.L@#@G...#
+#M###N#H###.#
+#o#m..#i#jk.#
+#############";
+        assert_eq!(Ok(72), shortest_path_four_robots(input));
     }
 }
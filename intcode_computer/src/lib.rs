@@ -1,8 +1,13 @@
-use mockstream::MockStream;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::convert::TryInto;
+use std::fmt;
+use std::io;
 use std::str::FromStr;
+use std::sync::mpsc::{Receiver, Sender};
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
 pub enum Operation {
     Add,
     Multiply,
@@ -16,6 +21,19 @@ pub enum Operation {
     End,
 }
 
+const ALL_OPERATIONS: [Operation; 10] = [
+    Operation::Add,
+    Operation::Multiply,
+    Operation::Input,
+    Operation::Output,
+    Operation::JumpIfTrue,
+    Operation::JumpIfFalse,
+    Operation::LessThan,
+    Operation::Equals,
+    Operation::AdjustRelativeBase,
+    Operation::End,
+];
+
 impl Operation {
     fn from_code(code: isize) -> Result<Operation, String> {
         let op_code = code % 100;
@@ -42,7 +60,25 @@ impl Operation {
             _ => 0,
         }
     }
-    fn apply(&self, computer: &mut Computer) -> Result<bool, String> {
+    /// Where this operation is counted in the profiler's `op_counts`
+    fn profiling_index(&self) -> usize {
+        match self {
+            Self::Add => 0,
+            Self::Multiply => 1,
+            Self::Input => 2,
+            Self::Output => 3,
+            Self::JumpIfTrue => 4,
+            Self::JumpIfFalse => 5,
+            Self::LessThan => 6,
+            Self::Equals => 7,
+            Self::AdjustRelativeBase => 8,
+            Self::End => 9,
+        }
+    }
+    fn apply<M: Memory, Io: IntcodeIo>(
+        &self,
+        computer: &mut Computer<M, Io>,
+    ) -> Result<bool, String> {
         match self {
             Operation::Add => {
                 computer.add()?;
@@ -77,7 +113,7 @@ impl Operation {
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum ParameterMode {
     PositionMode,
     ImmediateMode,
@@ -85,19 +121,20 @@ pub enum ParameterMode {
 }
 
 impl ParameterMode {
-    fn from_code(code: isize) -> Result<Vec<Self>, String> {
-        // Ignore the two rightmost difits which are for the op_code
-        let op_mode = (code - code % 100) / 100;
-        let s = op_mode.to_string();
-        s.chars()
-            .rev()
-            .map(|c| match c {
-                '0' => Ok(Self::PositionMode),
-                '1' => Ok(Self::ImmediateMode),
-                '2' => Ok(Self::RelativeMode),
-                _ => Err(format!("Invalid parameter mode in op code: {}", code)),
-            })
-            .collect()
+    /// The mode of the parameter at `offset` is the `offset`th digit of the op code, counting
+    /// from the right after ignoring the two rightmost digits which are for the op_code.
+    /// Leading zeros are omitted, so a missing digit means position mode.
+    fn from_code(code: isize, offset: usize) -> Result<Self, String> {
+        match Self::digit(code, offset) {
+            0 => Ok(Self::PositionMode),
+            1 => Ok(Self::ImmediateMode),
+            2 => Ok(Self::RelativeMode),
+            _ => Err(format!("Invalid parameter mode in op code: {}", code)),
+        }
+    }
+    fn digit(code: isize, offset: usize) -> isize {
+        let op_mode = code / 100;
+        (op_mode / 10_isize.pow(offset as u32 - 1)) % 10
     }
 }
 
@@ -109,8 +146,13 @@ impl Default for ParameterMode {
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub enum ComputationStatus {
+    /// The program can keep going
+    Running,
     StarvingForMockInput,
+    /// The program halted on op code 99
     Done,
+    /// The instruction pointer reached this breakpoint address
+    BreakpointHit(usize),
 }
 
 impl Default for ComputationStatus {
@@ -119,30 +161,224 @@ impl Default for ComputationStatus {
     }
 }
 
+impl ComputationStatus {
+    pub fn is_done(&self) -> bool {
+        *self == Self::Done
+    }
+    pub fn is_starving(&self) -> bool {
+        *self == Self::StarvingForMockInput
+    }
+}
+
+impl fmt::Display for ComputationStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Running => write!(f, "running"),
+            Self::StarvingForMockInput => write!(f, "starving for input"),
+            Self::Done => write!(f, "done"),
+            Self::BreakpointHit(address) => write!(f, "hit the breakpoint at {}", address),
+        }
+    }
+}
+
+/// What a computer driven with `resume` is waiting for
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Yield {
+    NeedInput,
+    Output(isize),
+    Halt,
+}
+
 const STARVING_ERROR: &'static str = "Starving for mock input";
 
+fn parse_input(token: &str) -> Result<isize, String> {
+    token
+        .parse()
+        .map_err(|e| format!("Invalid input '{}': {}", token, e))
+}
+
+/// Backing store for the computer's memory. Cells which were never written to read as 0.
+pub trait Memory {
+    fn read(&self, index: usize) -> isize;
+    fn write(&mut self, index: usize, datum: isize);
+    /// One past the highest address loaded or written to. Checked before every instruction, so it
+    /// should be cheap
+    fn end(&self) -> usize;
+}
+
+impl Memory for Vec<isize> {
+    fn read(&self, index: usize) -> isize {
+        self.get(index).cloned().unwrap_or(0)
+    }
+    fn write(&mut self, index: usize, datum: isize) {
+        if index >= self.len() {
+            // Only grow up to the written cell, so that the length keeps marking the end of the
+            // data. `resize` already reserves room for more ahead of time
+            self.resize(index + 1, 0);
+        }
+        self[index] = datum;
+    }
+    fn end(&self) -> usize {
+        self.len()
+    }
+}
+
+/// Only stores the cells that were written to, so writing to a huge address doesn't allocate
+/// everything below it
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SparseMemory {
+    cells: HashMap<usize, isize>,
+    /// One past the highest address written to, kept up to date rather than searched for
+    end: usize,
+}
+
+impl SparseMemory {
+    /// The number of cells stored
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+}
+
+impl Memory for SparseMemory {
+    fn read(&self, index: usize) -> isize {
+        self.cells.get(&index).cloned().unwrap_or(0)
+    }
+    fn write(&mut self, index: usize, datum: isize) {
+        self.cells.insert(index, datum);
+        self.end = self.end.max(index + 1);
+    }
+    fn end(&self) -> usize {
+        self.end
+    }
+}
+
+/// Where the computer reads its inputs from and writes its outputs to
+pub trait IntcodeIo {
+    /// The next input, or None if there is none available. An error if the input couldn't be read
+    fn read(&mut self) -> Result<Option<isize>, String>;
+    fn write(&mut self, value: isize) -> Result<(), String>;
+}
+
+/// In memory queues of pending inputs and outputs. This is what computers use by default.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct BufferIo {
+    pub input: VecDeque<isize>,
+    pub output: VecDeque<isize>,
+}
+
+impl IntcodeIo for BufferIo {
+    fn read(&mut self) -> Result<Option<isize>, String> {
+        Ok(self.input.pop_front())
+    }
+    fn write(&mut self, value: isize) -> Result<(), String> {
+        self.output.push_back(value);
+        Ok(())
+    }
+}
+
+/// Prompt the user for inputs on stdin and print outputs on stdout.
+/// Reading blocks until the user answers, so this is only used when opted into with `with_io`
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StdIo;
+
+impl IntcodeIo for StdIo {
+    /// None once stdin is closed
+    fn read(&mut self) -> Result<Option<isize>, String> {
+        println!("Please, enter input:");
+        let mut input = String::new();
+        let num_bytes = io::stdin()
+            .read_line(&mut input)
+            .map_err(|e| format!("Can't read stdin: {}", e))?;
+        if num_bytes == 0 {
+            return Ok(None);
+        }
+        parse_input(input.trim()).map(Some)
+    }
+    fn write(&mut self, value: isize) -> Result<(), String> {
+        println!("{}", value);
+        Ok(())
+    }
+}
+
+/// Talk to other computers running on different threads.
+/// Reading blocks until a value is sent, and returns None once every sender hung up.
+pub struct ChannelIo {
+    pub input: Receiver<isize>,
+    pub output: Sender<isize>,
+}
+
+impl IntcodeIo for ChannelIo {
+    fn read(&mut self) -> Result<Option<isize>, String> {
+        Ok(self.input.recv().ok())
+    }
+    fn write(&mut self, value: isize) -> Result<(), String> {
+        self.output
+            .send(value)
+            .map_err(|_| format!("Nobody is listening for output {}", value))
+    }
+}
+
 #[derive(Clone)]
-pub struct Computer {
-    pub data: Vec<isize>,
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Computer<M = Vec<isize>, Io = BufferIo> {
+    pub data: M,
     pub index: usize,
     pub relative_base: isize,
-    pub mock_io: Option<MockStream>,
+    pub io: Io,
+    /// Number of times each operation was executed. None unless profiling is enabled
+    pub op_counts: Option<[u64; 10]>,
+    breakpoints: HashSet<usize>,
 }
 
 impl Computer {
     pub fn from_data(data: Vec<isize>) -> Self {
+        Self::from_memory(data)
+    }
+}
+
+impl Computer<SparseMemory> {
+    /// A computer with sparse memory, for programs which write to very large addresses
+    pub fn with_sparse_memory(data: Vec<isize>) -> Self {
+        let mut memory = SparseMemory::default();
+        for (index, datum) in data.into_iter().enumerate() {
+            memory.write(index, datum);
+        }
+        Self::from_memory(memory)
+    }
+}
+
+impl<M: Memory> Computer<M> {
+    fn from_memory(data: M) -> Self {
         Self {
             data,
             index: 0,
             relative_base: 0,
-            mock_io: None,
+            io: BufferIo::default(),
+            op_counts: None,
+            breakpoints: HashSet::new(),
         }
     }
-    fn write_cell(&mut self, index: usize, datum: isize) {
-        if index >= self.data.len() {
-            self.data.resize(2 * index + 1, 0);
+}
+
+impl<M: Memory, Io: IntcodeIo> Computer<M, Io> {
+    /// The same computer, talking through a different io backend
+    pub fn with_io<NewIo: IntcodeIo>(self, io: NewIo) -> Computer<M, NewIo> {
+        Computer {
+            data: self.data,
+            index: self.index,
+            relative_base: self.relative_base,
+            io,
+            op_counts: self.op_counts,
+            breakpoints: self.breakpoints,
         }
-        self.data[index] = datum;
+    }
+    fn write_cell(&mut self, index: usize, datum: isize) {
+        self.data.write(index, datum);
     }
     fn write_at_offset(&mut self, offset: usize, datum: isize) -> Result<(), String> {
         let store_index: usize = self.address_at_offset(offset)?;
@@ -152,23 +388,33 @@ impl Computer {
     fn address_at_offset(&self, offset: usize) -> Result<usize, String> {
         let index = self.index + offset;
         let mode = self.mode_for_offset(offset)?;
-        match mode {
+        let address = match mode {
             ParameterMode::PositionMode => self.read_cell(index),
-            ParameterMode::ImmediateMode => panic!("Immediate mode is not supported for outputs"),
-            ParameterMode::RelativeMode => (self.read_cell(index) as isize + self.relative_base),
-        }
-        .try_into()
-        .map_err(|e| format!("Attempted to use negative integer as index: {}", e))
+            ParameterMode::ImmediateMode => {
+                return Err(format!(
+                    "Immediate mode is not supported for outputs: op code {} at index {}",
+                    self.read_cell(self.index),
+                    self.index
+                ))
+            }
+            ParameterMode::RelativeMode => self.read_cell(index) + self.relative_base,
+        };
+        address.try_into().map_err(|_| match mode {
+            ParameterMode::RelativeMode => format!(
+                "Attempted to access negative address {} with relative base {} at index {}",
+                address, self.relative_base, self.index
+            ),
+            _ => format!(
+                "Attempted to access negative address {} at index {}",
+                address, self.index
+            ),
+        })
     }
     fn mode_for_offset(&self, offset: usize) -> Result<ParameterMode, String> {
-        let modes = ParameterMode::from_code(self.read_cell(self.index))?;
-        Ok(modes
-            .get(offset - 1)
-            .cloned()
-            .unwrap_or(ParameterMode::default()))
+        ParameterMode::from_code(self.read_cell(self.index), offset)
     }
     fn read_cell(&self, index: usize) -> isize {
-        self.data.get(index).cloned().unwrap_or(0)
+        self.data.read(index)
     }
     fn read_at_offset(&self, offset: usize) -> Result<isize, String> {
         let mode = self.mode_for_offset(offset)?;
@@ -179,60 +425,34 @@ impl Computer {
             ParameterMode::ImmediateMode => Ok(self.read_cell(self.index + offset)),
         }
     }
-    fn apply<F>(&mut self, f: F) -> Result<(), String>
+    /// Store f of the first two parameters in the third. f returns None on overflow, which is an
+    /// error rather than silently wrapping around
+    fn apply<F>(&mut self, operation: &str, f: F) -> Result<(), String>
     where
-        F: Fn(isize, isize) -> isize,
+        F: Fn(isize, isize) -> Option<isize>,
     {
-        self.write_at_offset(3, f(self.read_at_offset(1)?, self.read_at_offset(2)?))
+        let (x, y) = (self.read_at_offset(1)?, self.read_at_offset(2)?);
+        let result = f(x, y).ok_or_else(|| {
+            format!(
+                "Overflow in {} of {} and {} at index {}",
+                operation, x, y, self.index
+            )
+        })?;
+        self.write_at_offset(3, result)
     }
     fn add(&mut self) -> Result<(), String> {
-        self.apply(|x, y| x + y)
+        self.apply("addition", isize::checked_add)
     }
     fn multiply(&mut self) -> Result<(), String> {
-        self.apply(|x, y| x * y)
-    }
-    fn user_input(&mut self) -> Result<isize, String> {
-        let mut input = String::new();
-        if let Some(stream) = &mut self.mock_io {
-            use std::io::Read;
-            let mut bytes = Vec::<u8>::new();
-            for byte in stream.bytes() {
-                let byte = byte.unwrap();
-                bytes.push(byte);
-                if byte == b"\n"[0] {
-                    break;
-                }
-            }
-            String::from_utf8(bytes)
-                .unwrap()
-                .trim()
-                .parse()
-                .map_err(|_| STARVING_ERROR.to_string())
-        } else {
-            use std::io;
-            println!("Please, enter input:");
-            io::stdin()
-                .read_line(&mut input)
-                .map_err(|e| format!("Error parsing user input: {}", e))?;
-            input
-                .trim()
-                .parse()
-                .map_err(|e| format!("Error parsing user input: {}", e))
-        }
+        self.apply("multiplication", isize::checked_mul)
     }
     fn input(&mut self) -> Result<(), String> {
-        let input = self.user_input()?;
+        let input = self.io.read()?.ok_or_else(|| STARVING_ERROR.to_string())?;
         self.write_at_offset(1, input)
     }
     fn output(&mut self) -> Result<(), String> {
-        let out = format!("{}\n", self.read_at_offset(1)?);
-        if let Some(stream) = &mut self.mock_io {
-            use std::io::Write;
-            stream.write_all(out.as_bytes()).unwrap();
-        } else {
-            print!("{}", out);
-        }
-        Ok(())
+        let out = self.read_at_offset(1)?;
+        self.io.write(out)
     }
     fn jump_if_true(&mut self) -> Result<bool, String> {
         if self.read_at_offset(1).map(|data| data != 0)? {
@@ -251,10 +471,13 @@ impl Computer {
         }
     }
     fn update_instruction_pointer(&mut self) -> Result<(), String> {
-        self.index = self
-            .read_at_offset(2)?
-            .try_into()
-            .map_err(|_e| "Instruction pointer may only be set to an unsigned value")?;
+        let target = self.read_at_offset(2)?;
+        self.index = target.try_into().map_err(|_e| {
+            format!(
+                "Can't jump to negative address {} from index {}",
+                target, self.index
+            )
+        })?;
         Ok(())
     }
     fn less_than(&mut self) -> Result<(), String> {
@@ -282,48 +505,619 @@ impl Computer {
         Ok(())
     }
     fn current_operation(&self) -> Result<Operation, String> {
-        Operation::from_code(self.read_cell(self.index))
+        if self.index >= self.data.end() {
+            return Err(format!(
+                "Ran off the end of memory without halting: instruction pointer at {}",
+                self.index
+            ));
+        }
+        let code = self.read_cell(self.index);
+        let op = Operation::from_code(code)?;
+        // Catch a bad mode before executing anything, rather than when its parameter gets used
+        for offset in 1..op.offset() {
+            ParameterMode::from_code(code, offset).map_err(|_| {
+                format!(
+                    "Invalid mode {} for parameter {} of op code {} at index {}",
+                    ParameterMode::digit(code, offset),
+                    offset,
+                    code,
+                    self.index
+                )
+            })?;
+        }
+        Ok(op)
+    }
+    fn profile(&mut self, op: Operation) {
+        if let Some(op_counts) = &mut self.op_counts {
+            op_counts[op.profiling_index()] += 1;
+        }
     }
+    /// Execute the instruction under the instruction pointer.
+    /// Returns Running if the computer can keep going
+    pub fn step(&mut self) -> Result<ComputationStatus, String> {
+        let op = self.current_operation()?;
+        if op == Operation::End {
+            self.profile(op);
+            return Ok(ComputationStatus::Done);
+        }
+        let result = op.apply(self);
+        if Err(STARVING_ERROR.to_string()) == result {
+            return Ok(ComputationStatus::StarvingForMockInput);
+        }
+        let did_jump = result?;
+        self.profile(op);
+        self.next(did_jump)?;
+        Ok(ComputationStatus::Running)
+    }
+    /// Run until the program halts or starves: never returns Running
     pub fn compute(&mut self) -> Result<ComputationStatus, String> {
-        let mut op = self.current_operation()?;
-        while op != Operation::End {
-            let result = op.apply(self);
-            if Err(STARVING_ERROR.to_string()) == result {
-                return Ok(ComputationStatus::StarvingForMockInput);
+        loop {
+            match self.step()? {
+                ComputationStatus::Running => continue,
+                status => return Ok(status),
             }
-            let did_jump = result?;
-            self.next(did_jump)?;
-            op = Operation::from_code(self.read_cell(self.index))?;
         }
-        Ok(ComputationStatus::Done)
     }
-    pub fn set_mock_io_input(&mut self, input: &str) {
-        if self.mock_io.is_none() {
-            self.mock_io = Some(MockStream::new());
+    /// Run until the computer needs an input, produces an output or halts.
+    /// `input` answers a previous `Yield::NeedInput`, and is an error if the computer isn't
+    /// waiting for one.
+    pub fn resume(&mut self, input: Option<isize>) -> Result<Yield, String> {
+        let mut input = input;
+        if input.is_some() && self.current_operation()? != Operation::Input {
+            return Err(format!(
+                "Resumed with an input, but the computer isn't waiting for one at index {}",
+                self.index
+            ));
+        }
+        loop {
+            let op = self.current_operation()?;
+            match op {
+                Operation::End => {
+                    self.profile(op);
+                    return Ok(Yield::Halt);
+                }
+                Operation::Input => {
+                    let value = match input.take() {
+                        Some(value) => value,
+                        None => return Ok(Yield::NeedInput),
+                    };
+                    self.write_at_offset(1, value)?;
+                    self.profile(op);
+                    self.next(false)?;
+                }
+                Operation::Output => {
+                    let value = self.read_at_offset(1)?;
+                    self.profile(op);
+                    self.next(false)?;
+                    return Ok(Yield::Output(value));
+                }
+                _ => {
+                    self.step()?;
+                }
+            }
         }
-        self.mock_io
-            .as_mut()
-            .unwrap()
-            .push_bytes_to_read(format!("{}\n", input).as_bytes());
     }
-    pub fn get_mock_io_output(&mut self) -> Result<String, String> {
-        match &mut self.mock_io {
-            Some(ref mut mock_io) => {
-                String::from_utf8(mock_io.pop_bytes_written()).map_err(|e| format!("{}", e))
+    pub fn add_breakpoint(&mut self, address: usize) {
+        self.breakpoints.insert(address);
+    }
+    pub fn remove_breakpoint(&mut self, address: usize) {
+        self.breakpoints.remove(&address);
+    }
+    /// Like compute, but stops as soon as the instruction pointer lands on a breakpoint.
+    /// At least one instruction is executed, so calling this again resumes past the breakpoint.
+    pub fn run_to_breakpoint(&mut self) -> Result<ComputationStatus, String> {
+        loop {
+            match self.step()? {
+                ComputationStatus::Running => (),
+                status => return Ok(status),
+            }
+            if self.breakpoints.contains(&self.index) {
+                return Ok(ComputationStatus::BreakpointHit(self.index));
+            }
+        }
+    }
+    /// Start counting how many times each operation gets executed. Profiling is off by default so
+    /// that it costs nothing when we don't need it.
+    pub fn enable_profiling(&mut self) {
+        if self.op_counts.is_none() {
+            self.op_counts = Some([0; 10]);
+        }
+    }
+    pub fn disable_profiling(&mut self) {
+        self.op_counts = None;
+    }
+    /// How many times each operation was executed since profiling was enabled
+    pub fn op_stats(&self) -> HashMap<Operation, u64> {
+        match &self.op_counts {
+            Some(op_counts) => ALL_OPERATIONS
+                .iter()
+                .map(|op| (*op, op_counts[op.profiling_index()]))
+                .collect(),
+            None => HashMap::new(),
+        }
+    }
+    pub fn total_instructions(&self) -> u64 {
+        self.op_counts
+            .map(|op_counts| op_counts.iter().sum())
+            .unwrap_or(0)
+    }
+}
+
+impl<M: Memory> Computer<M, BufferIo> {
+    /// Queue up the whitespace separated integers in `input`. Nothing is queued if any of them
+    /// isn't an integer
+    pub fn set_mock_io_input(&mut self, input: &str) -> Result<(), String> {
+        let input = input
+            .split_whitespace()
+            .map(parse_input)
+            .collect::<Result<Vec<_>, _>>()?;
+        self.io.input.extend(input);
+        Ok(())
+    }
+    /// Run until n more outputs were written, or the program halted or starved, and return the
+    /// outputs written on the way
+    pub fn run_collecting_outputs(&mut self, n: usize) -> Result<Vec<isize>, String> {
+        let already_written = self.io.output.len();
+        while self.io.output.len() < already_written + n {
+            if self.step()? != ComputationStatus::Running {
+                break;
             }
-            None => Err(format!("Attempting to get output from None mock_io")),
         }
+        Ok(self.io.output.drain(already_written..).collect())
+    }
+    /// Run on these inputs until the program halted or starved, and return what it output
+    pub fn run_and_collect(&mut self, input: &[isize]) -> Result<Vec<isize>, String> {
+        self.io.input.extend(input);
+        self.compute()?;
+        Ok(self.drain_outputs())
+    }
+    /// Whether the program outputs a copy of itself when run without input
+    pub fn is_quine(&mut self) -> bool {
+        let program = (0..self.data.end())
+            .map(|index| self.data.read(index))
+            .collect::<Vec<_>>();
+        self.run_and_collect(&[]) == Ok(program)
+    }
+    /// Collect the outputs written so far
+    pub fn drain_outputs(&mut self) -> Vec<isize> {
+        self.io.output.drain(..).collect()
+    }
+    /// Collect the outputs written so far as the text they spell in ASCII. Values which aren't
+    /// ASCII, like the answer some programs print after their picture, are written as numbers
+    pub fn ascii_screen(&mut self) -> String {
+        self.drain_outputs()
+            .into_iter()
+            .map(|out| match out {
+                0..=255 => (out as u8 as char).to_string(),
+                _ => out.to_string(),
+            })
+            .collect()
+    }
+    /// Like `ascii_screen`, split into lines, e.g. to read an interactive program's prompts
+    pub fn ascii_lines(&mut self) -> Vec<String> {
+        self.ascii_screen().lines().map(String::from).collect()
+    }
+    /// Collect the outputs written so far, one per line
+    pub fn get_mock_io_output(&mut self) -> Result<String, String> {
+        Ok(self
+            .drain_outputs()
+            .into_iter()
+            .map(|out| format!("{}\n", out))
+            .collect())
+    }
+}
+
+/// Drives a program which answers each input with a fixed number of outputs, like a robot
+/// reporting back after every command
+#[derive(Clone)]
+pub struct IntcodeRobot<M = Vec<isize>> {
+    pub computer: Computer<M>,
+}
+
+impl<M: Memory> IntcodeRobot<M> {
+    pub fn new(computer: Computer<M>) -> Self {
+        Self { computer }
+    }
+    /// Send an input and collect the outputs answering it. Fewer than expected if the program
+    /// halted or wants more input first
+    pub fn tick(&mut self, input: isize, expected_outputs: usize) -> Result<Vec<isize>, String> {
+        self.computer.io.input.push_back(input);
+        self.computer.run_collecting_outputs(expected_outputs)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<M, Io> Computer<M, Io>
+where
+    M: Memory + Serialize + serde::de::DeserializeOwned,
+    Io: IntcodeIo + Serialize + serde::de::DeserializeOwned,
+{
+    /// Checkpoint the full state of the computer as JSON so that it can be resumed later
+    pub fn save(&self) -> String {
+        serde_json::to_string(self).expect("A computer's state is always serializable")
+    }
+    pub fn load(s: &str) -> Result<Self, String> {
+        serde_json::from_str(s).map_err(|e| format!("Error loading computer state: {}", e))
+    }
+}
+
+/// Shows the registers, and the memory around the instruction pointer, which is marked with `>`
+impl<M: Memory, Io> fmt::Debug for Computer<M, Io> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Computer {{ index: {}, relative_base: {} }}",
+            self.index, self.relative_base
+        )?;
+        let window_end = (self.index + 8).min(self.data.end().max(self.index + 1));
+        for address in self.index.saturating_sub(4)..window_end {
+            let datum = self.data.read(address);
+            let marker = if address == self.index { '>' } else { ' ' };
+            writeln!(f, "{} {:>6}: {:>8} ({:#x})", marker, address, datum, datum)?;
+        }
+        Ok(())
     }
 }
 
+/// Comma or newline separated integers. Anything after `//` on a line is a comment
 impl FromStr for Computer {
     type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         Ok(Self::from_data(
-            s.split(|c| c == '\n' || c == ',')
-                .filter_map(|s| s.parse().ok())
-                .collect(),
+            s.lines()
+                .map(|line| line.split("//").next().unwrap_or(""))
+                .flat_map(|line| line.split(','))
+                .map(str::trim)
+                .filter(|token| !token.is_empty())
+                .map(|token| {
+                    token
+                        .parse()
+                        .map_err(|e| format!("Invalid token '{}' in program: {}", token, e))
+                })
+                .collect::<Result<_, _>>()?,
         ))
     }
 }
+
+/// Helpers for the tests of programs running on the computer
+#[cfg(any(test, feature = "testing"))]
+pub mod testing {
+    use super::*;
+
+    /// Run the program to completion on these inputs, and return everything it output
+    pub fn run_program(data: Vec<isize>, inputs: &[isize]) -> Vec<isize> {
+        let mut computer = Computer::from_data(data);
+        computer.io.input.extend(inputs);
+        assert_eq!(Ok(ComputationStatus::Done), computer.compute());
+        computer.io.output.into_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    fn modes(code: isize) -> Vec<ParameterMode> {
+        (1..=3)
+            .map(|offset| ParameterMode::from_code(code, offset).unwrap())
+            .collect()
+    }
+    #[test]
+    fn test_parameter_modes() {
+        use ParameterMode::*;
+        assert_eq!(vec![PositionMode, ImmediateMode, PositionMode], modes(1002));
+        assert_eq!(
+            vec![ImmediateMode, ImmediateMode, ImmediateMode],
+            modes(11101)
+        );
+        assert_eq!(vec![PositionMode, PositionMode, PositionMode], modes(3));
+        assert!(ParameterMode::from_code(-1002, 2).is_err());
+        assert!(ParameterMode::from_code(302, 1).is_err());
+    }
+    #[test]
+    fn test_run_collecting_outputs() {
+        // Output a pair of values for each input, twice, then halt
+        let mut computer = Computer::from_data(vec![
+            3, 15, 104, 1, 4, 15, 3, 15, 104, 2, 4, 15, 99, 0, 0, 0,
+        ]);
+        assert_eq!(Ok(vec![]), computer.run_collecting_outputs(2));
+        computer.set_mock_io_input("7 8").unwrap();
+        assert_eq!(Ok(vec![1, 7]), computer.run_collecting_outputs(2));
+        assert_eq!(Ok(vec![2]), computer.run_collecting_outputs(1));
+        assert_eq!(Ok(vec![8]), computer.run_collecting_outputs(2));
+        assert_eq!(Ok(vec![]), computer.run_collecting_outputs(2));
+    }
+    #[test]
+    fn test_intcode_robot() {
+        // Answer each input with the input and its double, until the input is 0
+        let mut robot = IntcodeRobot::new(Computer::from_data(vec![
+            3, 14, 4, 14, 1002, 14, 2, 15, 4, 15, 1005, 14, 0, 99, 0, 0,
+        ]));
+        assert_eq!(Ok(vec![3, 6]), robot.tick(3, 2));
+        assert_eq!(Ok(vec![-4, -8]), robot.tick(-4, 2));
+        assert_eq!(Ok(vec![0, 0]), robot.tick(0, 2));
+        assert_eq!(Ok(vec![]), robot.tick(1, 2));
+    }
+    #[test]
+    fn test_drain_outputs() {
+        let mut computer = Computer::from_data(vec![104, 7, 104, -3, 99]);
+        computer.compute().unwrap();
+        assert_eq!(vec![7, -3], computer.drain_outputs());
+        assert!(computer.drain_outputs().is_empty());
+    }
+    #[test]
+    fn test_run_and_collect() {
+        // Output the sum of two inputs
+        let mut computer = Computer::from_data(vec![3, 11, 3, 12, 1, 11, 12, 11, 4, 11, 99]);
+        assert_eq!(Ok(vec![5]), computer.run_and_collect(&[2, 3]));
+        let mut computer = Computer::from_data(vec![104, 3, 99]);
+        assert!(!computer.is_quine());
+    }
+    #[test]
+    fn test_ascii_screen() {
+        let mut computer = Computer::from_str("104,35,104,46,104,10,104,1000,104,-1,99").unwrap();
+        computer.compute().unwrap();
+        assert_eq!("#.\n1000-1", computer.ascii_screen());
+        assert_eq!("", computer.ascii_screen());
+    }
+    #[test]
+    fn test_ascii_lines() {
+        let mut computer = Computer::from_data(vec![104, 104, 104, 105, 104, 10, 99]);
+        computer.compute().unwrap();
+        assert_eq!(vec!["hi".to_string()], computer.ascii_lines());
+        assert!(computer.ascii_lines().is_empty());
+    }
+    #[test]
+    fn test_invalid_parameter_mode() {
+        // Multiply with an invalid mode for the parameter it writes to
+        let mut computer = Computer::from_data(vec![31002, 5, 6, 7, 99, 2, 3, 0]);
+        assert_eq!(
+            Err("Invalid mode 3 for parameter 3 of op code 31002 at index 0".to_string()),
+            computer.step()
+        );
+        assert_eq!(0, computer.index);
+        assert_eq!(0, computer.data[7]);
+    }
+    #[test]
+    fn test_overflow() {
+        let big = isize::MAX - 1;
+        let mut computer = Computer::from_data(vec![1102, big, big, 5, 99, 0]);
+        assert_eq!(
+            Err(format!(
+                "Overflow in multiplication of {} and {} at index 0",
+                big, big
+            )),
+            computer.compute()
+        );
+        let mut computer = Computer::from_data(vec![1101, big, 1, 5, 99, 0]);
+        assert_eq!(Ok(ComputationStatus::Done), computer.compute());
+        assert_eq!(isize::MAX, computer.data[5]);
+    }
+    #[test]
+    fn test_jump_to_negative_address() {
+        let mut computer = Computer::from_data(vec![1101, 1, 2, 9, 1105, 1, -3, 99, 0, 0]);
+        assert_eq!(
+            Err("Can't jump to negative address -3 from index 4".to_string()),
+            computer.compute()
+        );
+        // The target is read from the cell past the halt
+        let mut computer = Computer::from_data(vec![106, 0, 4, 99, -7]);
+        assert_eq!(
+            Err("Can't jump to negative address -7 from index 0".to_string()),
+            computer.compute()
+        );
+    }
+    #[test]
+    fn test_step() {
+        // Add 1 and 2, read an input, halt
+        let mut computer = Computer::from_data(vec![1101, 1, 2, 7, 3, 8, 99, 0, 0]);
+        assert_eq!(Ok(ComputationStatus::Running), computer.step());
+        assert_eq!(Ok(ComputationStatus::StarvingForMockInput), computer.step());
+        computer.set_mock_io_input("5").unwrap();
+        assert_eq!(Ok(ComputationStatus::Running), computer.step());
+        assert_eq!(Ok(ComputationStatus::Done), computer.step());
+        assert_eq!(3, computer.data[7]);
+        assert_eq!(5, computer.data[8]);
+    }
+    #[test]
+    fn test_invalid_mock_input() {
+        let mut computer = Computer::from_str("3,5,4,5,99,0").unwrap();
+        assert_eq!(
+            Err("Invalid input 'x': invalid digit found in string".to_string()),
+            computer.set_mock_io_input("1 x 3")
+        );
+        assert!(computer.io.input.is_empty());
+    }
+    #[test]
+    fn test_missing_input_starves_rather_than_blocking() {
+        let mut computer = Computer::from_str("3,5,4,5,99,0").unwrap();
+        assert_eq!(
+            Ok(ComputationStatus::StarvingForMockInput),
+            computer.compute()
+        );
+        assert_eq!(
+            Ok(ComputationStatus::StarvingForMockInput),
+            computer.compute()
+        );
+        computer.set_mock_io_input("12").unwrap();
+        assert_eq!(Ok(ComputationStatus::Done), computer.compute());
+        assert_eq!(vec![12], computer.drain_outputs());
+    }
+    #[test]
+    fn test_computation_status() {
+        assert!(ComputationStatus::Done.is_done());
+        assert!(!ComputationStatus::Done.is_starving());
+        assert!(ComputationStatus::StarvingForMockInput.is_starving());
+        assert!(!ComputationStatus::BreakpointHit(4).is_done());
+        assert_eq!("done", ComputationStatus::Done.to_string());
+        assert_eq!(
+            "hit the breakpoint at 4",
+            ComputationStatus::BreakpointHit(4).to_string()
+        );
+    }
+    #[test]
+    fn test_run_to_breakpoint() {
+        // (1 + 1 = 2), then (2 * 2 = 4)
+        let mut computer = Computer::from_data(vec![1, 0, 0, 0, 2, 0, 0, 3, 99]);
+        computer.add_breakpoint(4);
+        computer.add_breakpoint(8);
+        assert_eq!(
+            Ok(ComputationStatus::BreakpointHit(4)),
+            computer.run_to_breakpoint()
+        );
+        assert_eq!(4, computer.index);
+        assert_eq!(vec![2, 0, 0, 0, 2, 0, 0, 3, 99], computer.data);
+        computer.remove_breakpoint(8);
+        assert_eq!(Ok(ComputationStatus::Done), computer.run_to_breakpoint());
+        assert_eq!(vec![2, 0, 0, 4, 2, 0, 0, 3, 99], computer.data);
+    }
+    #[test]
+    fn test_immediate_mode_write_is_an_error() {
+        let mut computer = Computer::from_data(vec![11101, 1, 1, 0, 99]);
+        assert!(computer.compute().is_err());
+    }
+    #[test]
+    fn test_running_off_the_end_of_memory_is_an_error() {
+        let mut computer = Computer::from_data(vec![1, 0, 0, 0]);
+        assert!(computer.compute().is_err());
+        let mut computer = Computer::from_data(vec![1, 0, 0, 0, 99]);
+        assert_eq!(Ok(ComputationStatus::Done), computer.compute());
+    }
+    #[test]
+    fn test_debug_dump() {
+        let mut computer = Computer::from_data(vec![1, 0, 0, 0, 99]);
+        computer.compute().unwrap();
+        let dump = format!("{:?}", computer);
+        let lines = dump.lines().collect::<Vec<_>>();
+        assert_eq!(6, lines.len());
+        assert_eq!("Computer { index: 4, relative_base: 0 }", lines[0]);
+        assert_eq!("       0:        2 (0x2)", lines[1]);
+        assert_eq!(">      4:       99 (0x63)", lines[5]);
+    }
+    #[test]
+    fn test_resume() {
+        // Double the input
+        let mut computer = Computer::from_data(vec![3, 9, 1002, 9, 2, 9, 4, 9, 99, 0]);
+        assert_eq!(Ok(Yield::NeedInput), computer.resume(None));
+        assert_eq!(Ok(Yield::NeedInput), computer.resume(None));
+        assert_eq!(Ok(Yield::Output(42)), computer.resume(Some(21)));
+        assert!(computer.resume(Some(21)).is_err());
+        assert_eq!(Ok(Yield::Halt), computer.resume(None));
+        assert_eq!(Ok(Yield::Halt), computer.resume(None));
+    }
+    #[test]
+    fn test_parse_program_with_comments() {
+        let computer = Computer::from_str(
+            "// Double the input
+            3, 9,
+            1002, 9, 2, 9 // Multiply
+            4,9,99,
+            0 // Scratch space",
+        )
+        .unwrap();
+        assert_eq!(vec![3, 9, 1002, 9, 2, 9, 4, 9, 99, 0], computer.data);
+    }
+    #[test]
+    fn test_parse_program_with_invalid_token() {
+        assert!(Computer::from_str("1,0,0,0,99").is_ok());
+        assert!(Computer::from_str("1,0,12x,0,99").is_err());
+    }
+    #[test]
+    fn test_run_program() {
+        // Output the sum and the product of the two inputs, using the cells past the end
+        let program = vec![3, 17, 3, 18, 1, 17, 18, 19, 4, 19, 2, 17, 18, 19, 4, 19, 99];
+        assert_eq!(vec![7, 12], testing::run_program(program, &[3, 4]));
+    }
+    #[test]
+    fn test_negative_relative_address_is_an_error() {
+        // Set the relative base to -5, then output the cell at relative offset 2
+        let mut computer = Computer::from_data(vec![109, -5, 204, 2, 99]);
+        assert_eq!(
+            Err(
+                "Attempted to access negative address -3 with relative base -5 at index 2"
+                    .to_string()
+            ),
+            computer.compute()
+        );
+        let mut computer = Computer::from_data(vec![4, -1, 99]);
+        assert_eq!(
+            Err("Attempted to access negative address -1 at index 0".to_string()),
+            computer.compute()
+        );
+    }
+    #[test]
+    fn test_end_of_memory() {
+        // Store 7 + 8 at address 10, past the end of the program
+        let mut computer = Computer::from_data(vec![1101, 7, 8, 10, 99]);
+        assert_eq!(5, computer.data.end());
+        assert_eq!(Ok(ComputationStatus::Done), computer.compute());
+        assert_eq!(11, computer.data.end());
+        let mut computer = Computer::with_sparse_memory(vec![1101, 7, 8, 10, 99]);
+        assert_eq!(5, computer.data.end());
+        assert_eq!(Ok(ComputationStatus::Done), computer.compute());
+        assert_eq!(11, computer.data.end());
+    }
+    #[test]
+    fn test_write_to_huge_address_with_sparse_memory() {
+        // Store 7 + 8 a billion cells away, then output it
+        let mut computer =
+            Computer::with_sparse_memory(vec![1101, 7, 8, 1_000_000_000, 4, 1_000_000_000, 99]);
+        computer.set_mock_io_input("").unwrap();
+        assert_eq!(Ok(ComputationStatus::Done), computer.compute());
+        assert_eq!(Ok("15\n".to_string()), computer.get_mock_io_output());
+        assert_eq!(8, computer.data.len());
+        assert_eq!(1_000_000_001, computer.data.end());
+    }
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_save_and_load() {
+        // Day 09's quine outputs its own 16 cells
+        let quine = "109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99";
+        let uninterrupted = Computer::from_str(quine)
+            .unwrap()
+            .run_and_collect(&[])
+            .unwrap();
+        let mut computer = Computer::from_str(quine).unwrap();
+        let mut outputs = computer.run_collecting_outputs(5).unwrap();
+        assert_eq!(5, outputs.len());
+        let mut resumed = Computer::<Vec<isize>>::load(&computer.save()).unwrap();
+        outputs.extend(resumed.run_and_collect(&[]).unwrap());
+        assert_eq!(uninterrupted, outputs);
+    }
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_load_malformed_state() {
+        assert!(Computer::<Vec<isize>>::load("{\"data\": [99], \"index\":").is_err());
+        assert!(Computer::<Vec<isize>>::load("not json").is_err());
+    }
+    #[test]
+    fn test_channel_io() {
+        use std::sync::mpsc::channel;
+        // Double the input
+        let (input, receiver) = channel();
+        let (sender, output) = channel();
+        let mut computer =
+            Computer::from_data(vec![3, 9, 1002, 9, 2, 9, 4, 9, 99, 0]).with_io(ChannelIo {
+                input: receiver,
+                output: sender,
+            });
+        input.send(21).unwrap();
+        assert_eq!(Ok(ComputationStatus::Done), computer.compute());
+        assert_eq!(Ok(42), output.recv());
+    }
+    #[test]
+    fn test_channel_io_nobody_listening() {
+        use std::sync::mpsc::channel;
+        let (_input, receiver) = channel();
+        let (sender, output) = channel();
+        drop(output);
+        let mut computer = Computer::from_data(vec![104, 7, 99]).with_io(ChannelIo {
+            input: receiver,
+            output: sender,
+        });
+        assert_eq!(
+            Err("Nobody is listening for output 7".to_string()),
+            computer.compute()
+        );
+    }
+}
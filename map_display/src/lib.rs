@@ -1,8 +1,194 @@
-use direction::{CardinalDirection, Coord};
-use std::collections::HashMap;
+use direction::{CardinalDirection, CardinalDirectionIter, Coord};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::fmt::{self, Display, Formatter};
 use std::str::FromStr;
 
+/// The smallest box containing a set of coordinates, which can be grown one coordinate at a time
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Bounds {
+    /// Top left corner
+    pub min: Coord,
+    /// Bottom right corner
+    pub max: Coord,
+}
+
+impl Bounds {
+    pub fn new(coord: Coord) -> Self {
+        Self {
+            min: coord,
+            max: coord,
+        }
+    }
+    pub fn extend(&mut self, coord: Coord) {
+        self.min = Coord::new(self.min.x.min(coord.x), self.min.y.min(coord.y));
+        self.max = Coord::new(self.max.x.max(coord.x), self.max.y.max(coord.y));
+    }
+    pub fn contains(&self, coord: Coord) -> bool {
+        (self.min.x..=self.max.x).contains(&coord.x) && (self.min.y..=self.max.y).contains(&coord.y)
+    }
+}
+
+/// The top left and bottom right corners of the smallest box containing all these coordinates
+pub fn bounds<'a>(coords: impl Iterator<Item = &'a Coord>) -> Option<(Coord, Coord)> {
+    accumulate_bounds(coords).map(|bounds| (bounds.min, bounds.max))
+}
+
+fn accumulate_bounds<'a>(coords: impl Iterator<Item = &'a Coord>) -> Option<Bounds> {
+    coords.fold(None, |bounds, coord| match bounds {
+        None => Some(Bounds::new(*coord)),
+        Some(mut bounds) => {
+            bounds.extend(*coord);
+            Some(bounds)
+        }
+    })
+}
+
+/// Quarter turns about the origin. The y axis points down, as on screen, so clockwise turns east
+/// into south
+pub trait Rotate {
+    fn rotate_cw(self) -> Self;
+    fn rotate_ccw(self) -> Self;
+}
+
+impl Rotate for Coord {
+    fn rotate_cw(self) -> Self {
+        Coord::new(-self.y, self.x)
+    }
+    fn rotate_ccw(self) -> Self {
+        Coord::new(self.y, -self.x)
+    }
+}
+
+/// A coordinate ordered in reading order: top to bottom, then left to right. `Coord` comes from
+/// another crate, so it can't implement `Ord` itself; wrap it to sort coordinates or to key a
+/// `BTreeMap`
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct RowMajor(pub Coord);
+
+impl Ord for RowMajor {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.0.y, self.0.x).cmp(&(other.0.y, other.0.x))
+    }
+}
+
+impl PartialOrd for RowMajor {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Compass letters N/S/E/W, or U/D/L/R as used by wire and path puzzles where up is north
+pub fn parse_direction(c: char) -> Result<CardinalDirection, String> {
+    match c {
+        'N' | 'U' => Ok(CardinalDirection::North),
+        'S' | 'D' => Ok(CardinalDirection::South),
+        'E' | 'R' => Ok(CardinalDirection::East),
+        'W' | 'L' => Ok(CardinalDirection::West),
+        _ => Err(format!("Can't parse '{}' as a direction", c)),
+    }
+}
+
+/// A quarter turn, as seen by a robot facing some direction
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Turn {
+    Left,
+    Right,
+}
+
+impl Turn {
+    pub fn apply(self, facing: CardinalDirection) -> CardinalDirection {
+        match self {
+            Self::Left => facing.left90(),
+            Self::Right => facing.right90(),
+        }
+    }
+}
+
+impl Display for Turn {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let px = match self {
+            Self::Left => 'L',
+            Self::Right => 'R',
+        };
+        write!(f, "{}", px)
+    }
+}
+
+pub trait TurnToward {
+    /// The quarter turns which take us from facing self to facing target: none if we already face
+    /// it, one if it's to the side and two if it's behind us
+    fn turn_toward(self, target: CardinalDirection) -> Vec<Turn>;
+}
+
+impl TurnToward for CardinalDirection {
+    fn turn_toward(self, target: CardinalDirection) -> Vec<Turn> {
+        if self == target {
+            vec![]
+        } else if self.left90() == target {
+            vec![Turn::Left]
+        } else if self.right90() == target {
+            vec![Turn::Right]
+        } else {
+            vec![Turn::Right, Turn::Right]
+        }
+    }
+}
+
+/// All the coordinates reachable from start by stepping north, south, east or west onto passable
+/// tiles, start included. Empty if start itself isn't passable
+pub fn flood_fill<T>(
+    map: &HashMap<Coord, T>,
+    start: Coord,
+    passable: impl Fn(&T) -> bool,
+) -> HashSet<Coord> {
+    let is_passable = |coord: &Coord| map.get(coord).is_some_and(&passable);
+    let mut filled = HashSet::new();
+    if !is_passable(&start) {
+        return filled;
+    }
+    filled.insert(start);
+    let mut queue = VecDeque::new();
+    queue.push_back(start);
+    while let Some(coord) = queue.pop_front() {
+        for direction in CardinalDirectionIter::new() {
+            let neighbor = coord + direction.coord();
+            if is_passable(&neighbor) && filled.insert(neighbor) {
+                queue.push_back(neighbor);
+            }
+        }
+    }
+    filled
+}
+
+fn render<Content>(
+    map: &HashMap<Coord, Content>,
+    bounds: Option<Bounds>,
+    f: &mut Formatter<'_>,
+) -> fmt::Result
+where
+    Content: Display + Default,
+{
+    let Bounds { min, max } = match bounds {
+        Some(bounds) => bounds,
+        None => return Ok(()),
+    };
+    (min.y..=max.y)
+        .map(|y| {
+            (min.x..=max.x)
+                .map(|x| {
+                    write!(
+                        f,
+                        "{}",
+                        map.get(&Coord::new(x, y)).unwrap_or(&Content::default())
+                    )
+                })
+                .collect::<Result<(), _>>()?;
+            write!(f, "\r\n")
+        })
+        .collect::<Result<_, _>>()
+}
+
 pub struct MapDisplay<Content>(pub HashMap<Coord, Content>);
 
 impl<Content> Display for MapDisplay<Content>
@@ -10,26 +196,7 @@ where
     Content: Display + Default,
 {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        let cmp_x = |left: &&Coord, right: &&Coord| left.x.cmp(&right.x);
-        let cmp_y = |left: &&Coord, right: &&Coord| left.y.cmp(&right.y);
-        let min_x = self.0.keys().min_by(cmp_x).unwrap().x;
-        let max_x = self.0.keys().max_by(cmp_x).unwrap().x;
-        let min_y = self.0.keys().min_by(cmp_y).unwrap().y;
-        let max_y = self.0.keys().max_by(cmp_y).unwrap().y;
-        (min_y..=max_y)
-            .map(|y| {
-                (min_x..=max_x)
-                    .map(|x| {
-                        write!(
-                            f,
-                            "{}",
-                            self.0.get(&Coord::new(x, y)).unwrap_or(&Content::default())
-                        )
-                    })
-                    .collect::<Result<_, _>>()?;
-                write!(f, "\r\n")
-            })
-            .collect::<Result<_, _>>()
+        render(&self.0, accumulate_bounds(self.0.keys()), f)
     }
 }
 
@@ -52,3 +219,240 @@ where
         Ok(Self(map))
     }
 }
+
+impl<Content> MapDisplay<Content>
+where
+    Content: Display + Default + From<char>,
+{
+    /// Like `from_str`, but refuses ragged input: every row must be as long as the first one
+    pub fn from_str_strict(s: &str) -> Result<Self, String> {
+        let mut row_lengths = s
+            .trim()
+            .split('\n')
+            .map(|line| line.chars().count())
+            .enumerate();
+        if let Some((_, n_cols)) = row_lengths.next() {
+            if let Some((row, len)) = row_lengths.find(|(_, len)| *len != n_cols) {
+                return Err(format!(
+                    "Inconsistent row lengths: row 0 has {} cols while row {} has {} cols",
+                    n_cols, row, len
+                ));
+            }
+        }
+        Self::from_str(s)
+    }
+}
+
+/// A sparse 2D grid of tiles, with the usual helpers to look around a tile and display the grid
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Grid<T> {
+    tiles: HashMap<Coord, T>,
+    /// Kept up to date as tiles are set, so that displaying the grid doesn't need to look at
+    /// every tile to find its corners
+    bounds: Option<Bounds>,
+}
+
+impl<T> Default for Grid<T> {
+    fn default() -> Self {
+        Self {
+            tiles: HashMap::new(),
+            bounds: None,
+        }
+    }
+}
+
+impl<T> From<HashMap<Coord, T>> for Grid<T> {
+    fn from(tiles: HashMap<Coord, T>) -> Self {
+        let bounds = accumulate_bounds(tiles.keys());
+        Self { tiles, bounds }
+    }
+}
+
+impl<T> Grid<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn get(&self, coord: Coord) -> Option<&T> {
+        self.tiles.get(&coord)
+    }
+    /// Returns the tile which was previously at this coordinate, if any
+    pub fn set(&mut self, coord: Coord, tile: T) -> Option<T> {
+        match &mut self.bounds {
+            Some(bounds) => bounds.extend(coord),
+            None => self.bounds = Some(Bounds::new(coord)),
+        }
+        self.tiles.insert(coord, tile)
+    }
+    pub fn len(&self) -> usize {
+        self.tiles.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.tiles.is_empty()
+    }
+    pub fn iter(&self) -> impl Iterator<Item = (&Coord, &T)> {
+        self.tiles.iter()
+    }
+    pub fn into_map(self) -> HashMap<Coord, T> {
+        self.tiles
+    }
+    /// The top left and bottom right corners of the grid. None if the grid is empty
+    pub fn bounds(&self) -> Option<(Coord, Coord)> {
+        self.bounds.map(|bounds| (bounds.min, bounds.max))
+    }
+    /// The tiles North, East, South and West of this coordinate which are in the grid
+    pub fn neighbors4(&self, coord: Coord) -> impl Iterator<Item = (Coord, &T)> + '_ {
+        CardinalDirectionIter::new()
+            .map(move |direction| coord + direction.coord())
+            .filter_map(move |neighbor| self.get(neighbor).map(|tile| (neighbor, tile)))
+    }
+    /// The tiles surrounding this coordinate, diagonals included, which are in the grid
+    pub fn neighbors8(&self, coord: Coord) -> impl Iterator<Item = (Coord, &T)> + '_ {
+        (-1..=1)
+            .flat_map(|y| (-1..=1).map(move |x| Coord::new(x, y)))
+            .filter(|offset| *offset != Coord::default())
+            .map(move |offset| coord + offset)
+            .filter_map(move |neighbor| self.get(neighbor).map(|tile| (neighbor, tile)))
+    }
+}
+
+impl<T> Display for Grid<T>
+where
+    T: Display + Default,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        render(&self.tiles, self.bounds, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_bounds() {
+        let mut bounds = Bounds::new(Coord::new(1, 1));
+        bounds.extend(Coord::new(3, -1));
+        bounds.extend(Coord::new(2, 0));
+        assert_eq!(Coord::new(1, -1), bounds.min);
+        assert_eq!(Coord::new(3, 1), bounds.max);
+        assert!(bounds.contains(Coord::new(2, 1)));
+        assert!(!bounds.contains(Coord::new(0, 0)));
+        assert!(!bounds.contains(Coord::new(2, 2)));
+    }
+    #[test]
+    fn test_row_major() {
+        let mut coords = vec![
+            Coord::new(2, 1),
+            Coord::new(0, 2),
+            Coord::new(1, 1),
+            Coord::new(3, 0),
+        ];
+        coords.sort_by_key(|coord| RowMajor(*coord));
+        assert_eq!(
+            vec![
+                Coord::new(3, 0),
+                Coord::new(1, 1),
+                Coord::new(2, 1),
+                Coord::new(0, 2)
+            ],
+            coords
+        );
+        assert!(RowMajor(Coord::new(5, -1)) < RowMajor(Coord::new(-5, 0)));
+    }
+    #[test]
+    fn test_rotate() {
+        assert_eq!(Coord::new(0, 1), Coord::new(1, 0).rotate_cw());
+        assert_eq!(Coord::new(0, -1), Coord::new(1, 0).rotate_ccw());
+        assert_eq!(
+            CardinalDirection::East.right90().coord(),
+            CardinalDirection::East.coord().rotate_cw()
+        );
+        let coord = Coord::new(3, -2);
+        assert_eq!(coord, coord.rotate_cw().rotate_ccw());
+        assert_eq!(Coord::new(-3, 2), coord.rotate_cw().rotate_cw());
+    }
+    #[test]
+    fn test_parse_direction() {
+        use CardinalDirection::*;
+        for (letters, direction) in &[("NU", North), ("SD", South), ("ER", East), ("WL", West)] {
+            for c in letters.chars() {
+                assert_eq!(Ok(*direction), parse_direction(c));
+            }
+        }
+        assert_eq!(
+            Err("Can't parse 'x' as a direction".to_string()),
+            parse_direction('x')
+        );
+        assert!(parse_direction('u').is_err());
+    }
+    #[test]
+    fn test_turn_toward() {
+        use CardinalDirection::*;
+        assert_eq!(Vec::<Turn>::new(), North.turn_toward(North));
+        assert_eq!(vec![Turn::Left], North.turn_toward(West));
+        assert_eq!(vec![Turn::Right], North.turn_toward(East));
+        assert_eq!(vec![Turn::Right, Turn::Right], North.turn_toward(South));
+        for from in CardinalDirection::all() {
+            for to in CardinalDirection::all() {
+                let turns = from.turn_toward(to);
+                assert_eq!(
+                    to,
+                    turns.iter().fold(from, |facing, turn| turn.apply(facing))
+                );
+                let expected_len = if from == to {
+                    0
+                } else if from.opposite() == to {
+                    2
+                } else {
+                    1
+                };
+                assert_eq!(expected_len, turns.len());
+            }
+        }
+    }
+    #[test]
+    fn test_from_str_strict() {
+        let map = MapDisplay::<char>::from_str_strict("ab\ncd").unwrap();
+        assert_eq!(Some(&'c'), map.0.get(&Coord::new(0, 1)));
+        assert_eq!(
+            Err("Inconsistent row lengths: row 0 has 2 cols while row 1 has 3 cols".to_string()),
+            MapDisplay::<char>::from_str_strict("ab\ncde").map(|map| map.0)
+        );
+        assert!(MapDisplay::<char>::from_str("ab\ncde").is_ok());
+    }
+    #[test]
+    fn test_flood_fill() {
+        let map = MapDisplay::<char>::from_str("..#.\n..#.\n..#.").unwrap().0;
+        let left = flood_fill(&map, Coord::new(0, 0), |c| *c == '.');
+        assert_eq!(6, left.len());
+        assert!(left.iter().all(|coord| coord.x < 2));
+        let right = flood_fill(&map, Coord::new(3, 2), |c| *c == '.');
+        assert_eq!(
+            (0..3).map(|y| Coord::new(3, y)).collect::<HashSet<_>>(),
+            right
+        );
+        assert!(flood_fill(&map, Coord::new(2, 0), |c| *c == '.').is_empty());
+    }
+    #[test]
+    fn test_grid() {
+        let mut grid = Grid::new();
+        assert_eq!(None, grid.bounds());
+        grid.set(Coord::new(0, 0), 'a');
+        grid.set(Coord::new(1, 0), 'b');
+        grid.set(Coord::new(1, 1), 'c');
+        grid.set(Coord::new(-2, 3), 'd');
+        assert_eq!(Some(&'b'), grid.get(Coord::new(1, 0)));
+        assert_eq!(Some((Coord::new(-2, 0), Coord::new(1, 3))), grid.bounds());
+        let mut neighbors4 = grid
+            .neighbors4(Coord::new(1, 1))
+            .map(|(_, tile)| *tile)
+            .collect::<Vec<_>>();
+        neighbors4.sort();
+        assert_eq!(vec!['b'], neighbors4);
+        let mut neighbors8 = grid
+            .neighbors8(Coord::new(1, 1))
+            .map(|(_, tile)| *tile)
+            .collect::<Vec<_>>();
+        neighbors8.sort();
+        assert_eq!(vec!['a', 'b'], neighbors8);
+    }
+}
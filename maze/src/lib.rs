@@ -1,13 +1,20 @@
 #![deny(warnings)]
 
+pub use direction::CardinalDirection;
+use direction::CardinalDirectionIter;
 pub use direction::Coord;
-use direction::{CardinalDirection, CardinalDirectionIter};
-use map_display::MapDisplay;
+use map_display::{bounds, MapDisplay, RowMajor};
 pub use petgraph;
-use petgraph::algo::astar;
+use petgraph::algo::{astar, dijkstra};
+use petgraph::dot::Dot;
 pub use petgraph::graph::{DiGraph, NodeIndex};
-use std::collections::HashMap;
+use petgraph::visit::EdgeRef;
+use petgraph::Undirected;
+use std::cmp::Reverse;
+use std::collections::hash_map::Entry;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
 use std::fmt::{self, Debug, Display, Formatter};
+use std::hash::Hash;
 use std::str::FromStr;
 
 pub trait MazeTile {
@@ -16,6 +23,62 @@ pub trait MazeTile {
     /// Interesting tiles are tiles that should end up in the graph representation of the wall
     /// whether or not they are located at intersections or dead-ends in the maze
     fn is_interesting(self) -> bool;
+    /// The cost of stepping onto this tile. At least 1, or A*'s distance estimate would overshoot
+    fn cost(self) -> usize
+    where
+        Self: Sized,
+    {
+        1
+    }
+    /// How much we trust this tile when merging mazes: a tile never overwrites one of higher
+    /// priority. By default, walls beat interesting tiles which beat everything else
+    fn priority(self) -> usize
+    where
+        Self: Sized + Copy,
+    {
+        if self.is_wall() {
+            2
+        } else if self.is_interesting() {
+            1
+        } else {
+            0
+        }
+    }
+}
+
+/// The role of a tile in the topology of the maze
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum TileKind {
+    /// At most one way in or out
+    DeadEnd,
+    /// Exactly two ways in or out
+    Corridor,
+    /// More than two ways in or out
+    Intersection,
+    /// The tile itself is interesting, whatever its surroundings
+    Interesting,
+}
+
+/// How A* estimates the remaining distance to the destination
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Heuristic {
+    Manhattan,
+    /// Underestimates more than Manhattan in a maze where we can't move diagonally
+    Chebyshev,
+    /// Turns A* into Dijkstra's algorithm
+    Zero,
+}
+
+impl Heuristic {
+    fn estimate(self, from: Coord, to: Coord) -> usize {
+        let dx = (from.x - to.x).unsigned_abs() as usize;
+        let dy = (from.y - to.y).unsigned_abs() as usize;
+        match self {
+            Self::Manhattan => dx + dy,
+            Self::Chebyshev => dx.max(dy),
+            Self::Zero => 0,
+        }
+    }
 }
 
 #[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
@@ -37,6 +100,30 @@ struct Edge {
     weight: usize,
 }
 
+/// A tile classified by predicates, for mazes over tiles which don't implement `MazeTile`
+/// themselves
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct Classified<T> {
+    pub tile: T,
+    is_wall: bool,
+    is_interesting: bool,
+}
+
+impl<T> MazeTile for Classified<T> {
+    fn is_wall(self) -> bool {
+        self.is_wall
+    }
+    fn is_interesting(self) -> bool {
+        self.is_interesting
+    }
+}
+
+impl<T: Display> Display for Classified<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.tile)
+    }
+}
+
 impl<Content> FromStr for Maze<Content>
 where
     Content: Display + Default + From<char>,
@@ -60,16 +147,297 @@ where
     }
 }
 
-impl<MazeTile> Maze<MazeTile>
-where
-    MazeTile: crate::MazeTile + PartialEq + Display + Copy,
-{
+/// Building a maze and finding paths in its graph work with any tile
+impl<MazeTile> Maze<MazeTile> {
     pub fn new(map: HashMap<Coord, MazeTile>) -> Self {
         Self(map)
     }
+    /// GraphViz representation of the graph, with coordinates on the nodes and distances on the
+    /// edges
+    pub fn graph_to_dot(graph: &DiGraph<Coord, usize>) -> String {
+        let labelled = graph.map(
+            |_index, coord| format!("({}, {})", coord.x, coord.y),
+            |_index, weight| *weight,
+        );
+        format!("{}", Dot::new(&labelled))
+    }
+    /// A text representation which doesn't depend on how tiles are displayed: the top left corner
+    /// as "x,y" on the first line, then a character per tile, with a space where there is none
+    pub fn to_ascii(&self, glyph_of: impl Fn(&MazeTile) -> char) -> String {
+        match bounds(self.0.keys()) {
+            Some((min, _max)) => format!(
+                "{},{}\n{}",
+                min.x,
+                min.y,
+                self.ascii_rows(|_coord, tile| glyph_of(tile))
+            ),
+            None => String::new(),
+        }
+    }
+    /// A line of text per row of the map, with a space where there is no tile
+    fn ascii_rows(&self, glyph_at: impl Fn(Coord, &MazeTile) -> char) -> String {
+        let mut ascii = String::new();
+        if let Some((min, max)) = bounds(self.0.keys()) {
+            for y in min.y..=max.y {
+                ascii.extend((min.x..=max.x).map(|x| {
+                    let coord = Coord::new(x, y);
+                    self.0.get(&coord).map_or(' ', |tile| glyph_at(coord, tile))
+                }));
+                ascii.push('\n');
+            }
+        }
+        ascii
+    }
+    /// For checking a graph built from this maze against the map: each node is drawn as its index
+    /// in base 36 (which wraps around for large graphs), and followed by a line per node listing
+    /// where its edges lead and their weights
+    pub fn annotate_graph(
+        &self,
+        graph: &DiGraph<Coord, usize>,
+        glyph_of: impl Fn(&MazeTile) -> char,
+    ) -> String {
+        let labels = graph
+            .node_indices()
+            .map(|index| {
+                let label = std::char::from_digit(index.index() as u32 % 36, 36).unwrap();
+                (graph[index], label)
+            })
+            .collect::<HashMap<_, _>>();
+        let mut annotated =
+            self.ascii_rows(|coord, tile| labels.get(&coord).copied().unwrap_or(glyph_of(tile)));
+        for index in graph.node_indices() {
+            let coord = graph[index];
+            let mut edges = graph
+                .edges(index)
+                .map(|edge| (edge.target().index(), *edge.weight()))
+                .collect::<Vec<_>>();
+            edges.sort();
+            let edges = edges
+                .iter()
+                .map(|(target, weight)| format!(" {} ({})", target, weight))
+                .collect::<Vec<_>>();
+            annotated.push_str(&format!(
+                "{} ({}, {}):{}\n",
+                index.index(),
+                coord.x,
+                coord.y,
+                edges.join(",")
+            ));
+        }
+        annotated
+    }
+    /// Read back the output of `to_ascii`
+    pub fn from_ascii(s: &str, tile_of: impl Fn(char) -> MazeTile) -> Result<Self, String> {
+        let mut lines = s.lines();
+        let mut map = HashMap::new();
+        let corner = match lines.next() {
+            Some(corner) => corner,
+            None => return Ok(Self(map)),
+        };
+        let (x, y) = match corner
+            .split(',')
+            .map(|n| n.trim().parse::<i32>())
+            .collect::<Vec<_>>()[..]
+        {
+            [Ok(x), Ok(y)] => (x, y),
+            _ => return Err(format!("Invalid top left corner: '{}'", corner)),
+        };
+        for (row, line) in lines.enumerate() {
+            for (col, c) in line.chars().enumerate() {
+                if c != ' ' {
+                    let coord = Coord::new(x + col as i32, y + row as i32);
+                    map.insert(coord, tile_of(c));
+                }
+            }
+        }
+        Ok(Self(map))
+    }
+    /// Where to find each coordinate in a graph built without `as_graph_from_indexed`
+    pub fn index_nodes(graph: &DiGraph<Coord, usize>) -> HashMap<Coord, NodeIndex> {
+        graph
+            .node_indices()
+            .map(|index| (graph[index], index))
+            .collect()
+    }
+    pub fn shortest_path(
+        graph: &DiGraph<Coord, usize>,
+        start: Coord,
+        destination: Coord,
+    ) -> Option<usize> {
+        Self::shortest_path_indexed(graph, &Self::index_nodes(graph), start, destination)
+    }
+    /// Like `shortest_path`, looking up the endpoints in `indices` rather than searching the graph
+    pub fn shortest_path_indexed(
+        graph: &DiGraph<Coord, usize>,
+        indices: &HashMap<Coord, NodeIndex>,
+        start: Coord,
+        destination: Coord,
+    ) -> Option<usize> {
+        Self::shortest_path_stats_indexed(graph, indices, start, destination, Heuristic::Manhattan)
+            .map(|(cost, _expansions)| cost)
+    }
+    /// The cost of the shortest path from a node to each node of the graph, walking edges both ways
+    pub fn distances_from(graph: &DiGraph<Coord, usize>, from: Coord) -> HashMap<Coord, usize> {
+        Self::distances_from_indexed(graph, &Self::index_nodes(graph), from)
+    }
+    /// Like `distances_from`, looking up the start in `indices` rather than searching the graph
+    pub fn distances_from_indexed(
+        graph: &DiGraph<Coord, usize>,
+        indices: &HashMap<Coord, NodeIndex>,
+        from: Coord,
+    ) -> HashMap<Coord, usize> {
+        let start_index = match indices.get(&from) {
+            Some(index) => *index,
+            None => return HashMap::new(),
+        };
+        let undirected = graph.clone().into_edge_type::<Undirected>();
+        dijkstra(&undirected, start_index, None, |e| *e.weight())
+            .into_iter()
+            .map(|(index, distance)| (undirected[index], distance))
+            .collect()
+    }
+    /// The cost of the shortest path, and the nodes it goes through from start to destination
+    pub fn shortest_path_with_route(
+        graph: &DiGraph<Coord, usize>,
+        start: Coord,
+        destination: Coord,
+    ) -> Option<(usize, Vec<Coord>)> {
+        Self::shortest_path_with_route_indexed(graph, &Self::index_nodes(graph), start, destination)
+    }
+    /// Like `shortest_path_with_route`, looking up the endpoints in `indices` rather than searching
+    /// the graph
+    pub fn shortest_path_with_route_indexed(
+        graph: &DiGraph<Coord, usize>,
+        indices: &HashMap<Coord, NodeIndex>,
+        start: Coord,
+        destination: Coord,
+    ) -> Option<(usize, Vec<Coord>)> {
+        Self::astar_indexed(graph, indices, start, destination, Heuristic::Manhattan)
+            .map(|(cost, route, _expansions)| (cost, route))
+    }
+    /// The cost of the shortest path, and how many nodes A* expanded to find it
+    pub fn shortest_path_stats(
+        graph: &DiGraph<Coord, usize>,
+        start: Coord,
+        destination: Coord,
+        heuristic: Heuristic,
+    ) -> Option<(usize, usize)> {
+        Self::shortest_path_stats_indexed(
+            graph,
+            &Self::index_nodes(graph),
+            start,
+            destination,
+            heuristic,
+        )
+    }
+    /// Like `shortest_path_stats`, looking up the endpoints in `indices` rather than searching the
+    /// graph
+    pub fn shortest_path_stats_indexed(
+        graph: &DiGraph<Coord, usize>,
+        indices: &HashMap<Coord, NodeIndex>,
+        start: Coord,
+        destination: Coord,
+        heuristic: Heuristic,
+    ) -> Option<(usize, usize)> {
+        Self::astar_indexed(graph, indices, start, destination, heuristic)
+            .map(|(cost, _route, expansions)| (cost, expansions))
+    }
+    /// The cost of the shortest path, the nodes it goes through and how many nodes A* expanded.
+    /// None if either end isn't in the graph, or there is no way through
+    fn astar_indexed(
+        graph: &DiGraph<Coord, usize>,
+        indices: &HashMap<Coord, NodeIndex>,
+        start: Coord,
+        destination: Coord,
+        heuristic: Heuristic,
+    ) -> Option<(usize, Vec<Coord>, usize)> {
+        let start_index = *indices.get(&start)?;
+        let destination_index = *indices.get(&destination)?;
+        let mut expansions = 0;
+        astar(
+            &graph,
+            start_index,
+            |finish| {
+                expansions += 1;
+                finish == destination_index
+            },
+            |e| *e.weight(),
+            |n| heuristic.estimate(graph[n], destination),
+        )
+        .map(|(weight, path)| {
+            let route = path.into_iter().map(|index| graph[index]).collect();
+            (weight, route, expansions)
+        })
+    }
+}
+
+impl<T> Maze<Classified<T>> {
+    /// Build a maze over any tile, e.g. `char` or `bool`, without implementing `MazeTile` for it
+    pub fn from_map_with(
+        map: HashMap<Coord, T>,
+        is_wall: impl Fn(&T) -> bool,
+        is_interesting: impl Fn(&T) -> bool,
+    ) -> Self {
+        Self(
+            map.into_iter()
+                .map(|(coord, tile)| {
+                    let classified = Classified {
+                        is_wall: is_wall(&tile),
+                        is_interesting: is_interesting(&tile),
+                        tile,
+                    };
+                    (coord, classified)
+                })
+                .collect(),
+        )
+    }
+}
+
+/// Looking for a given tile needs to compare tiles
+impl<MazeTile> Maze<MazeTile>
+where
+    MazeTile: crate::MazeTile + PartialEq + Copy,
+{
     pub fn find_tile(&self, tile: MazeTile) -> Option<Coord> {
         self.find_tiles(&|t| t == tile).get(0).cloned()
     }
+}
+
+/// Counting tiles needs to hash them
+impl<MazeTile> Maze<MazeTile>
+where
+    MazeTile: Eq + Hash + Clone,
+{
+    /// How many cells hold each tile
+    pub fn tile_histogram(&self) -> HashMap<MazeTile, usize> {
+        let mut histogram = HashMap::new();
+        for tile in self.0.values() {
+            *histogram.entry(tile.clone()).or_insert(0) += 1;
+        }
+        histogram
+    }
+}
+
+/// Walking the maze needs to know where the walls and interesting tiles are
+impl<MazeTile> Maze<MazeTile>
+where
+    MazeTile: crate::MazeTile + Copy,
+{
+    /// Add the tiles of another, partially explored, maze to this one. Where both mazes know a
+    /// tile, the one with the highest priority wins
+    pub fn merge(&mut self, other: &Self) {
+        for (coord, tile) in &other.0 {
+            let _ = self
+                .0
+                .entry(*coord)
+                .and_modify(|known| {
+                    if tile.priority() >= known.priority() {
+                        *known = *tile;
+                    }
+                })
+                .or_insert(*tile);
+        }
+    }
     pub fn find_tiles(&self, filter: &dyn Fn(MazeTile) -> bool) -> Vec<Coord> {
         self.0
             .iter()
@@ -77,12 +445,20 @@ where
             .map(|(coord, _)| coord.clone())
             .collect()
     }
+    /// Like `find_tiles`, but in reading order (top to bottom, then left to right) rather than
+    /// in whichever order the map happens to store them
+    pub fn find_tiles_sorted(&self, filter: &dyn Fn(MazeTile) -> bool) -> Vec<Coord> {
+        let mut tiles = self.find_tiles(filter);
+        tiles.sort_by_key(|coord| RowMajor(*coord));
+        tiles
+    }
+    /// The tiles in the graph matching the filter, in reading order
     pub fn find_reachable_tiles(
         &self,
         graph: &DiGraph<Coord, usize>,
         filter: &dyn Fn(MazeTile) -> bool,
     ) -> Vec<Coord> {
-        graph
+        let mut tiles = graph
             .node_indices()
             .filter_map(|index| graph.node_weight(index))
             .filter(|coord| {
@@ -90,8 +466,45 @@ where
                 filter(tile)
             })
             .cloned()
+            .collect::<Vec<_>>();
+        tiles.sort_by_key(|coord| RowMajor(*coord));
+        tiles
+    }
+    /// Interesting tiles which are on the map, but can't be reached from `start`
+    pub fn unreachable_interesting_tiles(&self, start: Coord) -> Vec<Coord> {
+        let graph = self.as_graph_from(start);
+        let reachable = graph
+            .node_indices()
+            .filter_map(|index| graph.node_weight(index))
+            .collect::<HashSet<_>>();
+        self.find_tiles(&|tile| tile.is_interesting())
+            .into_iter()
+            .filter(|coord| !reachable.contains(coord))
             .collect()
     }
+    /// Whether the maze is surrounded by walls, meaning that no open tile is next to a coordinate
+    /// which isn't on the map.
+    /// Off-map coordinates are treated as walls when walking the maze, which is only right for
+    /// enclosed mazes.
+    pub fn is_enclosed(&self) -> bool {
+        self.0
+            .iter()
+            .filter(|(_, tile)| !tile.is_wall())
+            .all(|(coord, _)| {
+                CardinalDirectionIter::new()
+                    .all(|direction| self.0.contains_key(&(*coord + direction.coord())))
+            })
+    }
+    /// The open tiles one step away from `from` after walking in the `facing` direction, without
+    /// turning back to where we came from. Useful to write directed walks through the maze
+    pub fn step(&self, from: Coord, facing: CardinalDirection) -> Vec<(CardinalDirection, Coord)> {
+        self.reachable_neighbors(DirectedCoord {
+            coord: from,
+            direction: Some(facing),
+        })
+        .collect()
+    }
+    /// Off-map coordinates are not reachable
     fn reachable_neighbors(
         &self,
         point: DirectedCoord,
@@ -119,9 +532,30 @@ where
         tile.is_interesting()
     }
 
+    /// Classify a coordinate according to the number of open tiles around it, regardless of
+    /// where we came from
+    pub fn classify(&self, coord: Coord) -> TileKind {
+        let point = DirectedCoord {
+            coord,
+            direction: None,
+        };
+        if self.0.get(&coord).is_some_and(|tile| tile.is_interesting()) {
+            return TileKind::Interesting;
+        }
+        match self.num_reachable_neighbors(point) {
+            0 | 1 => TileKind::DeadEnd,
+            2 => TileKind::Corridor,
+            _ => TileKind::Intersection,
+        }
+    }
+
     fn find_next_node(&self, point: DirectedCoord) -> (DirectedCoord, usize) {
         let mut point = point;
-        let mut weight = if point.direction.is_some() { 1 } else { 0 };
+        let mut weight = if point.direction.is_some() {
+            self.0[&point.coord].cost()
+        } else {
+            0
+        };
         while !(self.is_dead_end(point)
             || self.is_intersection(point)
             || self.is_interesting(point))
@@ -131,7 +565,7 @@ where
                 direction: Some(direction),
                 coord,
             };
-            weight += 1;
+            weight += self.0[&coord].cost();
         }
         (point, weight)
     }
@@ -155,56 +589,483 @@ where
             )
             .collect::<Vec<_>>()
     }
-    fn as_index(point: Coord, nodes: &Vec<Coord>) -> u32 {
-        nodes.iter().position(|p| *p == point).unwrap() as u32
-    }
     // Represent the maze as a graph of intersections, with the distance between intersections on
     // the edges
     pub fn as_graph_from(&self, coord: Coord) -> DiGraph<Coord, usize> {
+        self.as_graph_from_indexed(coord).0
+    }
+    /// Like `as_graph_from`, along with where to find each coordinate in the graph
+    pub fn as_graph_from_indexed(
+        &self,
+        coord: Coord,
+    ) -> (DiGraph<Coord, usize>, HashMap<Coord, NodeIndex>) {
         let edges = self.build_edges_from(DirectedCoord {
             coord,
             direction: None,
         });
-
-        let mut nodes = std::iter::once(coord)
-            .chain(edges.iter().map(|(edge, _point)| edge.target))
-            .collect::<Vec<_>>();
-        nodes.dedup();
-        let mut graph = DiGraph::<Coord, usize>::from_edges(edges.iter().map(|(edge, _point)| {
-            (
-                Self::as_index(edge.origin, &nodes),
-                Self::as_index(edge.target, &nodes),
-                edge.weight,
-            )
-        }));
-        for (node, point) in graph.node_weights_mut().zip(nodes.iter()) {
-            *node = point.clone();
+        let mut graph = DiGraph::<Coord, usize>::new();
+        let mut indices = HashMap::new();
+        for node in std::iter::once(coord).chain(edges.iter().map(|(edge, _point)| edge.target)) {
+            let _ = indices.entry(node).or_insert_with(|| graph.add_node(node));
         }
-        graph
+        for (edge, _point) in &edges {
+            graph.add_edge(indices[&edge.origin], indices[&edge.target], edge.weight);
+        }
+        (graph, indices)
     }
-    pub fn shortest_path(
-        graph: &DiGraph<Coord, usize>,
-        start: Coord,
-        destination: Coord,
-    ) -> Option<usize> {
-        let start_index = graph
+    /// The longest of the shortest paths between any two nodes reachable from start
+    pub fn diameter(&self, start: Coord) -> usize {
+        // Walk edges both ways, converting the graph once rather than for each node
+        let undirected = self.as_graph_from(start).into_edge_type::<Undirected>();
+        undirected
             .node_indices()
-            .find(|index| graph.node_weight(*index) == Some(&start))
-            .unwrap();
+            .flat_map(|index| dijkstra(&undirected, index, None, |e| *e.weight()).into_values())
+            .max()
+            .unwrap_or(0)
+    }
+    /// The tiles from one coordinate to another, both included, with as few steps as possible.
+    /// Empty if there is no way through
+    fn route_between(&self, from: Coord, to: Coord) -> Vec<Coord> {
+        let mut came_from = HashMap::new();
+        let mut to_visit = VecDeque::new();
+        came_from.insert(from, from);
+        to_visit.push_back(from);
+        while let Some(coord) = to_visit.pop_front() {
+            if coord == to {
+                let mut route = vec![to];
+                let mut coord = to;
+                while coord != from {
+                    coord = came_from[&coord];
+                    route.push(coord);
+                }
+                route.reverse();
+                return route;
+            }
+            let point = DirectedCoord {
+                coord,
+                direction: None,
+            };
+            for (_, neighbor) in self.reachable_neighbors(point) {
+                if let Entry::Vacant(entry) = came_from.entry(neighbor) {
+                    entry.insert(coord);
+                    to_visit.push_back(neighbor);
+                }
+            }
+        }
+        Vec::new()
+    }
+    /// Run A* directly on the tiles, so that only the part of the maze between start and
+    /// destination gets explored, rather than building the graph of the whole maze first
+    pub fn shortest_path_direct(&self, start: Coord, destination: Coord) -> Option<usize> {
+        let estimate = |coord: Coord| coord.manhattan_distance(destination) as usize;
+        let mut best_costs = HashMap::new();
+        let mut to_visit = BinaryHeap::new();
+        best_costs.insert(start, 0);
+        // Coord isn't Ord, so the heap holds its components
+        to_visit.push(Reverse((estimate(start), 0, start.x, start.y)));
+        while let Some(Reverse((_, cost, x, y))) = to_visit.pop() {
+            let coord = Coord::new(x, y);
+            if coord == destination {
+                return Some(cost);
+            }
+            if best_costs[&coord] < cost {
+                // We already found a better way here
+                continue;
+            }
+            let point = DirectedCoord {
+                coord,
+                direction: None,
+            };
+            for (_, neighbor) in self.reachable_neighbors(point) {
+                let cost = cost + self.0[&neighbor].cost();
+                if best_costs.get(&neighbor).is_none_or(|best| cost < *best) {
+                    best_costs.insert(neighbor, cost);
+                    to_visit.push(Reverse((
+                        estimate(neighbor) + cost,
+                        cost,
+                        neighbor.x,
+                        neighbor.y,
+                    )));
+                }
+            }
+        }
+        None
+    }
+}
 
-        astar(
-            &graph,
-            start_index,
-            |finish| graph.node_weight(finish) == Some(&destination),
-            |e| *e.weight(),
-            |n| {
-                let cost = graph
-                    .node_weight(n)
-                    .unwrap()
-                    .manhattan_distance(destination) as usize;
-                cost
-            },
-        )
-        .map(|(weight, _path)| weight)
+/// Drawing a path over the maze needs to display its tiles
+impl<MazeTile> Maze<MazeTile>
+where
+    MazeTile: crate::MazeTile + Copy + Default + Display,
+{
+    /// Render the maze with the tiles along the path drawn as `·`, leaving the map untouched.
+    /// Consecutive coordinates of the path needn't be neighbors, e.g. nodes of the graph: the
+    /// tiles in between are filled in. Both ends keep their own tile, to show where the path goes
+    pub fn display_with_path(&self, path: &[Coord]) -> String {
+        let mut on_path = path
+            .windows(2)
+            .flat_map(|pair| self.route_between(pair[0], pair[1]))
+            .collect::<HashSet<_>>();
+        if let (Some(first), Some(last)) = (path.first(), path.last()) {
+            on_path.remove(first);
+            on_path.remove(last);
+        }
+        let (min, max) = match bounds(self.0.keys()) {
+            Some(bounds) => bounds,
+            None => return String::new(),
+        };
+        let mut rendered = String::new();
+        for y in min.y..=max.y {
+            for x in min.x..=max.x {
+                let coord = Coord::new(x, y);
+                let tile = format!("{}", self.0.get(&coord).copied().unwrap_or_default());
+                if on_path.contains(&coord) {
+                    rendered.extend(tile.chars().map(|_| '·'));
+                } else {
+                    rendered.push_str(&tile);
+                }
+            }
+            rendered.push_str("\r\n");
+        }
+        rendered
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+    enum Tile {
+        #[default]
+        Wall,
+        Open,
+        Key,
+        Mud,
+    }
+    impl Display for Tile {
+        fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+            let c = match self {
+                Self::Wall => '#',
+                Self::Open => '.',
+                Self::Key => 'k',
+                Self::Mud => '~',
+            };
+            write!(f, "{}", c)
+        }
+    }
+    impl From<char> for Tile {
+        fn from(c: char) -> Self {
+            match c {
+                '.' => Self::Open,
+                'k' => Self::Key,
+                '~' => Self::Mud,
+                _ => Self::Wall,
+            }
+        }
+    }
+    impl crate::MazeTile for Tile {
+        fn is_wall(self) -> bool {
+            self == Self::Wall
+        }
+        fn is_interesting(self) -> bool {
+            self == Self::Key
+        }
+        fn cost(self) -> usize
+        where
+            Self: Sized,
+        {
+            if self == Self::Mud {
+                5
+            } else {
+                1
+            }
+        }
+    }
+    const MAZE: &str = "\
+#########
+#.......#
+#.#####.#
+#.#k..#.#
+#.###.#.#
+#.....#.#
+#########";
+
+    #[test]
+    fn test_shortest_path_direct() {
+        let maze = Maze::<Tile>::from_str(MAZE).unwrap();
+        let start = Coord::new(1, 1);
+        let key = Coord::new(3, 3);
+        let graph = maze.as_graph_from(start);
+        assert_eq!(Some(12), Maze::<Tile>::shortest_path(&graph, start, key));
+        assert_eq!(Some(12), maze.shortest_path_direct(start, key));
+        assert_eq!(Some(0), maze.shortest_path_direct(start, start));
+        assert_eq!(None, maze.shortest_path_direct(start, Coord::new(0, 0)));
+    }
+    #[test]
+    fn test_tile_histogram() {
+        let maze = Maze::new(
+            vec![
+                (Coord::new(0, 0), Tile::Wall),
+                (Coord::new(1, 0), Tile::Open),
+                (Coord::new(2, 0), Tile::Key),
+                (Coord::new(3, 0), Tile::Wall),
+                (Coord::new(0, 1), Tile::Wall),
+            ]
+            .into_iter()
+            .collect(),
+        );
+        let histogram = maze.tile_histogram();
+        assert_eq!(3, histogram.len());
+        assert_eq!(Some(&3), histogram.get(&Tile::Wall));
+        assert_eq!(Some(&1), histogram.get(&Tile::Open));
+        assert_eq!(Some(&1), histogram.get(&Tile::Key));
+        assert_eq!(None, histogram.get(&Tile::Mud));
+        assert!(Maze::<Tile>::new(HashMap::new())
+            .tile_histogram()
+            .is_empty());
+    }
+    #[test]
+    fn test_tile_cost() {
+        let maze = Maze::<Tile>::from_str("#####\n#.~k#\n#####").unwrap();
+        let start = Coord::new(1, 1);
+        let key = Coord::new(3, 1);
+        let graph = maze.as_graph_from(start);
+        assert_eq!(Some(6), Maze::<Tile>::shortest_path(&graph, start, key));
+        assert_eq!(Some(6), maze.shortest_path_direct(start, key));
+    }
+    #[test]
+    fn test_unreachable_interesting_tiles() {
+        let maze = Maze::<Tile>::from_str("#######\n#.k#k.#\n#######").unwrap();
+        assert_eq!(
+            vec![Coord::new(4, 1)],
+            maze.unreachable_interesting_tiles(Coord::new(1, 1))
+        );
+        assert!(maze
+            .unreachable_interesting_tiles(Coord::new(5, 1))
+            .contains(&Coord::new(2, 1)));
+    }
+    #[test]
+    fn test_is_enclosed() {
+        assert!(Maze::<Tile>::from_str(MAZE).unwrap().is_enclosed());
+        assert!(!Maze::<Tile>::from_str("###\n#..\n###")
+            .unwrap()
+            .is_enclosed());
+    }
+    #[test]
+    fn test_shortest_path_stats() {
+        let maze = Maze::<Tile>::from_str(MAZE).unwrap();
+        let start = Coord::new(1, 1);
+        let key = Coord::new(3, 3);
+        let graph = maze.as_graph_from(start);
+        let stats = |heuristic| Maze::<Tile>::shortest_path_stats(&graph, start, key, heuristic);
+        let (manhattan_cost, manhattan_expansions) = stats(Heuristic::Manhattan).unwrap();
+        let (chebyshev_cost, _) = stats(Heuristic::Chebyshev).unwrap();
+        let (dijkstra_cost, dijkstra_expansions) = stats(Heuristic::Zero).unwrap();
+        assert_eq!(12, manhattan_cost);
+        assert_eq!(12, chebyshev_cost);
+        assert_eq!(12, dijkstra_cost);
+        assert!(manhattan_expansions <= dijkstra_expansions);
+    }
+    #[test]
+    fn test_display_with_path() {
+        let maze = Maze::<Tile>::from_str(MAZE).unwrap();
+        let start = Coord::new(1, 1);
+        let key = Coord::new(3, 3);
+        let graph = maze.as_graph_from(start);
+        let (cost, route) = Maze::<Tile>::shortest_path_with_route(&graph, start, key).unwrap();
+        assert_eq!(12, cost);
+        assert_eq!(Some(&start), route.first());
+        assert_eq!(Some(&key), route.last());
+        assert_eq!(
+            "\
+#########\r
+#.......#\r
+#·#####.#\r
+#·#k··#.#\r
+#·###·#.#\r
+#·····#.#\r
+#########\r
+",
+            maze.display_with_path(&route)
+        );
+        // The map itself is untouched
+        assert_eq!(
+            format!("{}", Maze::<Tile>::from_str(MAZE).unwrap()),
+            format!("{}", maze)
+        );
+    }
+    #[test]
+    fn test_ascii() {
+        let maze = Maze::<Tile>::from_str(MAZE).unwrap();
+        let glyph_of = |tile: &Tile| format!("{}", tile).chars().next().unwrap();
+        let ascii = maze.to_ascii(glyph_of);
+        assert!(ascii.starts_with("0,0\n#########\n#.......#\n"));
+        let parsed = Maze::from_ascii(&ascii, Tile::from).unwrap();
+        assert_eq!(maze.0, parsed.0);
+        assert!(Maze::from_ascii("nowhere\n#", Tile::from).is_err());
+    }
+    #[test]
+    fn test_annotate_graph() {
+        let maze = Maze::<Tile>::from_str("#######\n#....k#\n###.###\n###.###\n#######").unwrap();
+        let graph = maze.as_graph_from(Coord::new(1, 1));
+        let glyph_of = |tile: &Tile| format!("{}", tile).chars().next().unwrap();
+        let annotated = maze.annotate_graph(&graph, glyph_of);
+        assert_eq!(
+            "\
+#######
+#0.1.2#
+###.###
+###3###
+#######
+0 (1, 1): 1 (2)
+1 (3, 1): 2 (2), 3 (2)
+2 (5, 1):
+3 (3, 3):
+",
+            annotated
+        );
+    }
+    #[test]
+    fn test_as_graph_from_indexed() {
+        let maze = Maze::<Tile>::from_str(MAZE).unwrap();
+        let start = Coord::new(1, 1);
+        let (graph, indices) = maze.as_graph_from_indexed(start);
+        assert_eq!(graph.node_count(), indices.len());
+        for (coord, index) in &indices {
+            assert_eq!(Some(coord), graph.node_weight(*index));
+        }
+        assert!(indices.contains_key(&Coord::new(3, 3)));
+        assert_eq!(graph.edge_count(), maze.as_graph_from(start).edge_count());
+        assert_eq!(indices, Maze::<Tile>::index_nodes(&graph));
+        let key = Coord::new(3, 3);
+        assert_eq!(
+            Maze::<Tile>::shortest_path(&graph, start, key),
+            Maze::<Tile>::shortest_path_indexed(&graph, &indices, start, key)
+        );
+        assert_eq!(
+            Maze::<Tile>::shortest_path_with_route(&graph, start, key),
+            Maze::<Tile>::shortest_path_with_route_indexed(&graph, &indices, start, key)
+        );
+        assert_eq!(
+            Maze::<Tile>::distances_from(&graph, key),
+            Maze::<Tile>::distances_from_indexed(&graph, &indices, key)
+        );
+        assert_eq!(
+            None,
+            Maze::<Tile>::shortest_path_indexed(&graph, &indices, start, Coord::new(0, 0))
+        );
+    }
+    #[test]
+    fn test_diameter() {
+        let maze = Maze::<Tile>::from_str("#############\n#...........#\n#############").unwrap();
+        assert_eq!(10, maze.diameter(Coord::new(1, 1)));
+        assert_eq!(10, maze.diameter(Coord::new(4, 1)));
+        let maze = Maze::<Tile>::from_str(MAZE).unwrap();
+        let start = Coord::new(1, 1);
+        let graph = maze.as_graph_from(start);
+        let distances = Maze::<Tile>::distances_from(&graph, Coord::new(3, 3));
+        assert_eq!(Some(&12), distances.get(&start));
+        assert!(Maze::<Tile>::distances_from(&graph, Coord::new(0, 0)).is_empty());
+    }
+    #[test]
+    fn test_graph_to_dot() {
+        let maze = Maze::<Tile>::from_str("#####\n#.~k#\n#####").unwrap();
+        let graph = maze.as_graph_from(Coord::new(1, 1));
+        let dot = Maze::<Tile>::graph_to_dot(&graph);
+        assert!(dot.starts_with("digraph {"));
+        assert!(dot.contains("label=\"(1, 1)\""));
+        assert!(dot.contains("label=\"(3, 1)\""));
+        assert!(dot.contains("label=\"6\""));
+    }
+    #[test]
+    fn test_maze_of_tiles_without_display() {
+        #[derive(Copy, Clone)]
+        struct Open(bool);
+        impl crate::MazeTile for Open {
+            fn is_wall(self) -> bool {
+                !self.0
+            }
+            fn is_interesting(self) -> bool {
+                false
+            }
+        }
+        let map = (0..4)
+            .map(|x| (Coord::new(x, 0), Open(x != 3)))
+            .collect::<HashMap<_, _>>();
+        let maze = Maze::new(map);
+        let graph = maze.as_graph_from(Coord::new(0, 0));
+        let destination = Coord::new(2, 0);
+        assert_eq!(
+            Some(2),
+            Maze::<Open>::shortest_path(&graph, Coord::new(0, 0), destination)
+        );
+        assert_eq!(
+            Some(2),
+            maze.shortest_path_direct(Coord::new(0, 0), destination)
+        );
+    }
+    #[test]
+    fn test_find_tiles_sorted() {
+        let maze = Maze::<Tile>::from_str("#####\n#k.k#\n#k###\n#####").unwrap();
+        assert_eq!(
+            vec![Coord::new(1, 1), Coord::new(3, 1), Coord::new(1, 2)],
+            maze.find_tiles_sorted(&|tile| tile == Tile::Key)
+        );
+    }
+    #[test]
+    fn test_step() {
+        let maze = Maze::<Tile>::from_str("#####\n#...#\n#.#.#\n#####").unwrap();
+        // Arriving at the corner from the south, the only way on is east
+        assert_eq!(
+            vec![(CardinalDirection::East, Coord::new(2, 1))],
+            maze.step(Coord::new(1, 1), CardinalDirection::North)
+        );
+        // Walking east along the top corridor, we never step back west
+        assert_eq!(
+            vec![(CardinalDirection::East, Coord::new(3, 1))],
+            maze.step(Coord::new(2, 1), CardinalDirection::East)
+        );
+    }
+    #[test]
+    fn test_merge() {
+        let mut maze = Maze::<Tile>::from_str("#k.").unwrap();
+        let other = Maze::<Tile>::from_str("..~#").unwrap();
+        maze.merge(&other);
+        assert_eq!(
+            vec![Tile::Wall, Tile::Key, Tile::Mud, Tile::Wall],
+            (0..4)
+                .map(|x| maze.0[&Coord::new(x, 0)])
+                .collect::<Vec<_>>()
+        );
+    }
+    #[test]
+    fn test_from_map_with() {
+        let map = "#####\n#a..#\n##.b#\n#####"
+            .lines()
+            .enumerate()
+            .flat_map(|(y, line)| {
+                line.chars()
+                    .enumerate()
+                    .map(move |(x, c)| (Coord::new(x as i32, y as i32), c))
+            })
+            .collect();
+        let maze = Maze::from_map_with(map, |c| *c == '#', char::is_ascii_lowercase);
+        let start = maze.find_tiles(&|tile| tile.tile == 'a')[0];
+        let destination = maze.find_tiles(&|tile| tile.tile == 'b')[0];
+        assert_eq!(Some(3), maze.shortest_path_direct(start, destination));
+        assert_eq!(
+            "#####\n#a..#\n##.b#\n#####",
+            format!("{}", maze).replace("\r", "").trim()
+        );
+    }
+    #[test]
+    fn test_classify() {
+        let maze = Maze::<Tile>::from_str("#####\n#..k#\n##.##\n#####").unwrap();
+        assert_eq!(TileKind::DeadEnd, maze.classify(Coord::new(1, 1)));
+        assert_eq!(TileKind::Intersection, maze.classify(Coord::new(2, 1)));
+        assert_eq!(TileKind::Interesting, maze.classify(Coord::new(3, 1)));
+        assert_eq!(TileKind::DeadEnd, maze.classify(Coord::new(2, 2)));
+        let maze = Maze::<Tile>::from_str(MAZE).unwrap();
+        assert_eq!(TileKind::Corridor, maze.classify(Coord::new(1, 1)));
     }
 }
@@ -0,0 +1,75 @@
+/// Apply `step` from `initial` until `stop` holds for the previous and the next state. Returns
+/// the last state along with the number of steps taken. Never returns if `stop` never holds
+pub fn iterate_until<S: PartialEq>(
+    initial: S,
+    step: impl Fn(&S) -> S,
+    stop: impl Fn(&S, &S) -> bool,
+) -> (S, usize) {
+    let mut state = initial;
+    let mut num_steps = 0;
+    loop {
+        let next = step(&state);
+        num_steps += 1;
+        let done = stop(&state, &next);
+        state = next;
+        if done {
+            return (state, num_steps);
+        }
+    }
+}
+
+/// Brent's algorithm: returns (mu, lambda) where mu is the index of the first state of the cycle
+/// and lambda its length
+pub fn find_cycle<S: Eq + Clone>(initial: S, step: impl Fn(&S) -> S) -> (usize, usize) {
+    // Find the cycle length by racing a hare which the tortoise teleports to at every power of two
+    let ((_power, lambda, _tortoise, _hare), _num_steps) = iterate_until(
+        (1, 0, initial.clone(), initial.clone()),
+        |(power, lambda, tortoise, hare)| {
+            if power == lambda {
+                (power * 2, 1, hare.clone(), step(hare))
+            } else {
+                (*power, lambda + 1, tortoise.clone(), step(hare))
+            }
+        },
+        |_, (_, _, tortoise, hare)| tortoise == hare,
+    );
+    // With the hare lambda steps ahead, they first meet at the start of the cycle
+    let mut hare = initial.clone();
+    for _ in 0..lambda {
+        hare = step(&hare);
+    }
+    if initial == hare {
+        return (0, lambda);
+    }
+    let (_state, mu) = iterate_until(
+        (initial, hare),
+        |(tortoise, hare)| (step(tortoise), step(hare)),
+        |_, (tortoise, hare)| tortoise == hare,
+    );
+    (mu, lambda)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_iterate_until() {
+        // 40, 20, 10, 5, 2, 1, 0, 0
+        let halve = |x: &usize| x / 2;
+        assert_eq!(
+            (0, 7),
+            iterate_until(40, halve, |previous, next| previous == next)
+        );
+        assert_eq!((5, 3), iterate_until(40, halve, |_, next| next % 2 == 1));
+        let rotate = |x: &usize| (x + 1) % 3;
+        assert_eq!((1, 3), iterate_until(1, rotate, |_, next| *next == 1));
+    }
+    #[test]
+    fn test_find_cycle() {
+        // 0, 1, 2, 3, 4, 5, 2, 3, ...
+        let step = |x: &usize| if *x < 5 { x + 1 } else { 2 };
+        assert_eq!((2, 4), find_cycle(0, step));
+        assert_eq!((0, 4), find_cycle(3, step));
+        assert_eq!((0, 1), find_cycle(7, |x: &usize| *x));
+    }
+}
@@ -0,0 +1,10 @@
+use std::fmt::Display;
+use std::time::Instant;
+
+/// Compute a result, then print it along with how long it took, as `label: result (ms)`
+pub fn timed<T: Display>(label: &str, f: impl FnOnce() -> T) -> T {
+    let start = Instant::now();
+    let result = f();
+    println!("{}: {} ({}ms)", label, result, start.elapsed().as_millis());
+    result
+}